@@ -1,28 +1,264 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use dnspod::source::DEFAULT_IP_SERVICE_URL;
+use dnspod::{Cidr, CloudProvider, DuplicateStrategy, OutageAction, ShutdownAction};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::str::FromStr;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// The domain name, e.g., "example.com"
-    #[arg(short, long, env = "DNSPOD_DOMAIN")]
-    pub domain: String,
+    /// The domain name, e.g., "example.com" or "例子.中国"
+    #[arg(short, long, env = "DNSPOD_DOMAIN", required_unless_present = "name")]
+    pub domain: Option<String>,
     /// The sub-domain name, e.g., "home"
-    #[arg(short, long, env = "DNSPOD_SUB_DOMAIN")]
-    pub sub_domain: String,
+    #[arg(
+        short,
+        long,
+        env = "DNSPOD_SUB_DOMAIN",
+        required_unless_present = "name"
+    )]
+    pub sub_domain: Option<String>,
+    /// The full name to update instead of --domain and --sub-domain, e.g., "home.example.com.cn",
+    /// "example.com" or "*.example.com". The zone is looked up in the DNSPod account.
+    #[arg(short, long, env = "DNSPOD_NAME", conflicts_with_all = ["domain", "sub_domain"])]
+    pub name: Option<String>,
     /// Your DNSPod API token, in "ID,Token" format
     #[arg(short, long, env = "DNSPOD_TOKEN")]
     pub token: String,
     /// Check interval in seconds. Set to 0 to run only once.
     #[arg(short, long, env = "UPDATE_INTERVAL_SECS", default_value_t = 10)]
     pub interval: u64,
+    /// Publish a new address only after it was detected this many times in a row, or
+    /// after --stable-secs, whichever comes first.
+    #[arg(long, env = "STABLE_COUNT", default_value_t = 1)]
+    pub stable_count: u32,
+    /// Publish a new address only after it stayed unchanged for this many seconds, or
+    /// after --stable-count detections, whichever comes first.
+    #[arg(long, env = "STABLE_SECS", default_value_t = 0)]
+    pub stable_secs: u64,
+    /// Minimum number of seconds between two modifications of the same record.
+    #[arg(long, env = "MIN_UPDATE_INTERVAL_SECS", default_value_t = 0)]
+    pub min_update_interval: u64,
+    /// What to do with a record whose address family disappears: keep, disable or delete.
+    /// A family has disappeared when the host has no route for it; a failing IP source
+    /// alone is only retried.
+    #[arg(long, env = "ON_OUTAGE", default_value_t = OutageAction::Keep)]
+    pub on_outage: OutageAction,
+    /// Seconds an address family must stay gone before --on-outage applies.
+    #[arg(long, env = "OUTAGE_GRACE_SECS", default_value_t = 600)]
+    pub outage_grace: u64,
+    /// What to do with the records on SIGTERM or SIGINT in loop mode: keep, delete or park
+    /// (point them at --park-ip/--park-ip6). They are restored on the next start without
+    /// waiting for flap protection; for deleted records this needs --state-file.
+    #[arg(long, env = "ON_SHUTDOWN", default_value_t = ShutdownAction::Keep)]
+    pub on_shutdown: ShutdownAction,
+    /// IPv4 address that A records point to while parked, e.g., "192.0.2.1".
+    #[arg(long, env = "PARK_IP")]
+    pub park_ip: Option<Ipv4Addr>,
+    /// IPv6 address that AAAA records point to while parked, e.g., "100::".
+    #[arg(long, env = "PARK_IP6")]
+    pub park_ip6: Option<Ipv6Addr>,
+    /// Seconds to wait on shutdown for the running check to finish and the records to be
+    /// deleted or parked before exiting anyway.
+    #[arg(long, env = "SHUTDOWN_TIMEOUT_SECS", default_value_t = 10)]
+    pub shutdown_timeout: u64,
+    /// Maximum number of DNSPod API calls per minute, shared by all records.
+    #[arg(long, env = "API_RATE_LIMIT", default_value_t = 30)]
+    pub api_rate_limit: u32,
+    /// Maximum number of DNSPod API calls in a burst.
+    #[arg(long, env = "API_BURST", default_value_t = 5)]
+    pub api_burst: u32,
+    /// How to handle several A or AAAA records under the sub-domain: first (manage the lowest ID),
+    /// all (update every one) or collapse (delete all but one).
+    #[arg(long, env = "DUPLICATES", default_value_t = DuplicateStrategy::First)]
+    pub duplicates: DuplicateStrategy,
+    /// Record ID to prefer as the managed record when duplicates exist. Can be repeated.
+    /// An ID that matches no A or AAAA record on the line is ignored with a warning.
+    #[arg(long = "record-id", env = "RECORD_IDS", value_delimiter = ',')]
+    pub record_ids: Vec<String>,
+    /// Re-read the records from DNSPod every this many seconds to detect and undo changes made
+    /// elsewhere, e.g. in the DNSPod console. Set to 0 to disable.
+    #[arg(long, env = "RECONCILE_INTERVAL_SECS", default_value_t = 3600)]
+    pub reconcile_interval: u64,
+    /// Keep the zone, record IDs and published addresses in this file, so that runs while it
+    /// is fresh (e.g. single-shot runs from cron) make no API calls if the address is unchanged.
+    /// Held back changes and outage timers are kept too, so they carry over between runs.
+    #[arg(long, env = "STATE_FILE")]
+    pub state_file: Option<PathBuf>,
+    /// Seconds the state file is trusted after the records were last read from DNSPod.
+    #[arg(long, env = "STATE_TTL_SECS", default_value_t = 3600)]
+    pub state_ttl: u64,
+    /// Detect addresses normally, but only log the DNSPod changes and notifications that would
+    /// be sent, with secrets redacted.
+    #[arg(long, env = "DRY_RUN", default_value_t = false)]
+    pub dry_run: bool,
+    /// Mark records created by this instance with "managed-by=ddns host=<OWNER_ID>" and
+    /// never modify records that lack the marker.
+    #[arg(long, env = "OWNER_ID")]
+    pub owner_id: Option<String>,
+    /// Take over records without the ownership marker and mark them. Requires --owner-id.
+    #[arg(
+        long,
+        env = "ADOPT_FOREIGN",
+        default_value_t = false,
+        requires = "owner_id"
+    )]
+    pub adopt_foreign: bool,
+    /// Delete a CNAME or URL forwarding record at the name instead of stopping with an error.
+    /// NS delegations are never replaced. With --owner-id, records of other owners are only
+    /// replaced together with --adopt-foreign.
+    #[arg(long, env = "REPLACE_CONFLICTING", default_value_t = false)]
+    pub replace_conflicting: bool,
+    /// Check immediately when the kernel reports an address or default route change (Linux only).
+    /// The regular interval keeps running as a safety net and can be set much longer.
+    /// Has no effect with --interval 0.
+    #[arg(long, env = "WATCH_NETWORK", default_value_t = false)]
+    pub watch: bool,
     /// Enable IPv6 (AAAA record) update.
     #[arg(long, env = "ENABLE_IPV6", default_value_t = false)]
     pub ipv6: bool,
+    /// Publish this IPv4 address instead of detecting one, then exit, e.g., from a PPP ip-up hook.
+    /// The address must still pass the address policy: documentation ranges such as
+    /// 203.0.113.0/24 are denied by default, see --allow-cidr and --no-default-deny.
+    #[arg(long)]
+    pub ip: Option<Ipv4Addr>,
+    /// Publish this IPv6 address instead of detecting one, then exit. Can be combined with --ip.
+    /// The address must pass the address policy, like --ip.
+    #[arg(long)]
+    pub ip6: Option<Ipv6Addr>,
 
-    /// Your notify http server url for update ip
+    /// Where to detect the public IPv4 address.
+    #[arg(long, env = "IP_SOURCE", value_enum, default_value_t = SourceKind::Http)]
+    pub ip_source: SourceKind,
+    /// Where to detect the public IPv6 address: http or aws; the other clouds' metadata
+    /// services do not report one.
+    #[arg(long, env = "IP6_SOURCE", value_parser = parse_ip6_source, default_value = "http")]
+    pub ip6_source: SourceKind,
+    /// Override the cloud metadata service address, e.g., "http://127.0.0.1:8080".
+    #[arg(long, env = "METADATA_URL")]
+    pub metadata_url: Option<String>,
+    /// URL of the service used to detect the public IP address.
+    #[arg(long, env = "IP_URL", default_value = DEFAULT_IP_SERVICE_URL)]
+    pub ip_url: String,
+    /// HTTP method used for the IP detection request.
+    #[arg(long, env = "IP_METHOD", default_value = "GET")]
+    pub ip_method: String,
+    /// Extra header for the IP detection request, in "Name: Value" format. Can be repeated.
+    #[arg(long = "ip-header")]
+    pub ip_headers: Vec<String>,
+    /// Regex to extract the IP from the response (first capture group, or the whole match).
+    #[arg(long, env = "IP_REGEX", conflicts_with = "ip_json_pointer")]
+    pub ip_regex: Option<String>,
+    /// JSON pointer to the IP in a JSON response, e.g., "/ip".
+    #[arg(long, env = "IP_JSON_POINTER")]
+    pub ip_json_pointer: Option<String>,
+
+    /// CIDR that is always allowed, even inside a denied range. Can be repeated.
+    #[arg(long = "allow-cidr", env = "ALLOW_CIDRS", value_delimiter = ',')]
+    pub allow_cidrs: Vec<Cidr>,
+    /// CIDR that must never be published. Can be repeated.
+    #[arg(long = "deny-cidr", env = "DENY_CIDRS", value_delimiter = ',')]
+    pub deny_cidrs: Vec<Cidr>,
+    /// Do not deny private, CGNAT, loopback, link-local and documentation ranges by default.
+    #[arg(long, env = "NO_DEFAULT_DENY", default_value_t = false)]
+    pub no_default_deny: bool,
+
+    /// Publish the address seen through a specific uplink, e.g.,
+    /// "sub=telecom,iface=wan1" or "sub=home,line=联通,addr=198.51.100.2".
+    /// Keys: sub, line, iface, addr, addr6. Can be repeated; replaces the default record.
+    /// With --name, sub takes a full name as well.
+    #[arg(long = "uplink", env = "UPLINKS", value_delimiter = ';')]
+    pub uplinks: Vec<Uplink>,
+
+    /// Your notify http server url for update ip; {IP_ADDRESS} and {DOMAIN} are replaced
     #[arg(long, env = "HTTP_URL")]
     pub http_url: Option<String>,
     /// Your notify http server access token
     #[arg(long, env = "HTTP_TOKEN")]
     pub http_token: Option<String>,
 }
+
+/// The kind of source used to detect a public address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SourceKind {
+    /// The HTTP service configured with the --ip-* options.
+    Http,
+    /// The AWS EC2 instance metadata service (IMDSv2).
+    Aws,
+    /// The Tencent Cloud instance metadata service.
+    Tencent,
+    /// The Alibaba Cloud instance metadata service.
+    Alibaba,
+}
+
+/// (Private) Parses --ip6-source, rejecting metadata services without IPv6 addresses.
+fn parse_ip6_source(s: &str) -> Result<SourceKind, String> {
+    let kind = <SourceKind as ValueEnum>::from_str(s, true)?;
+    match kind.provider() {
+        Some(provider) if !provider.supports_ipv6() => Err(format!(
+            "{:?} metadata does not report a public IPv6 address; use http or aws",
+            provider
+        )),
+        _ => Ok(kind),
+    }
+}
+
+impl SourceKind {
+    /// The cloud provider behind this source, if it is a metadata source.
+    pub fn provider(self) -> Option<CloudProvider> {
+        match self {
+            SourceKind::Http => None,
+            SourceKind::Aws => Some(CloudProvider::Aws),
+            SourceKind::Tencent => Some(CloudProvider::Tencent),
+            SourceKind::Alibaba => Some(CloudProvider::Alibaba),
+        }
+    }
+}
+
+/// A WAN uplink whose public address is detected and published independently.
+#[derive(Debug, Clone, Default)]
+pub struct Uplink {
+    /// The sub-domain to update; defaults to `--sub-domain`.
+    pub sub_domain: Option<String>,
+    /// The DNSPod record line, e.g., "电信"; defaults to "默认".
+    pub line: Option<String>,
+    /// Network interface that detection requests are bound to (SO_BINDTODEVICE).
+    pub interface: Option<String>,
+    /// Local IPv4 source address for detection requests.
+    pub address: Option<IpAddr>,
+    /// Local IPv6 source address for detection requests.
+    pub address6: Option<IpAddr>,
+}
+
+impl FromStr for Uplink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut uplink = Uplink::default();
+        for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got '{}'", pair))?;
+            let value = value.trim();
+            match key.trim() {
+                "sub" => uplink.sub_domain = Some(value.to_string()),
+                "line" => uplink.line = Some(value.to_string()),
+                "iface" => uplink.interface = Some(value.to_string()),
+                "addr" => match value.parse::<IpAddr>() {
+                    Ok(ip @ IpAddr::V4(_)) => uplink.address = Some(ip),
+                    _ => return Err(format!("'{}' is not an IPv4 address", value)),
+                },
+                "addr6" => match value.parse::<IpAddr>() {
+                    Ok(ip @ IpAddr::V6(_)) => uplink.address6 = Some(ip),
+                    _ => return Err(format!("'{}' is not an IPv6 address", value)),
+                },
+                other => return Err(format!("unknown uplink key '{}'", other)),
+            }
+        }
+        if uplink.interface.is_none() && uplink.address.is_none() && uplink.address6.is_none() {
+            return Err("an uplink needs at least one of iface, addr or addr6".to_string());
+        }
+        Ok(uplink)
+    }
+}
@@ -1,4 +1,28 @@
+use std::time::Duration;
+
 pub const API_BASE: &str = "https://dnsapi.cn";
+/// The record line that every DNSPod plan supports.
+pub const DEFAULT_RECORD_LINE: &str = "默认";
+pub(crate) const API_DOMAIN_LIST: &str = "/Domain.List";
 pub(crate) const API_RECORD_LIST: &str = "/Record.List";
+pub(crate) const API_RECORD_INFO: &str = "/Record.Info";
 pub(crate) const API_RECORD_MODIFY: &str = "/Record.Modify";
 pub(crate) const API_RECORD_CREATE: &str = "/Record.Create";
+pub(crate) const API_RECORD_STATUS: &str = "/Record.Status";
+pub(crate) const API_RECORD_REMOVE: &str = "/Record.Remove";
+pub(crate) const API_RECORD_REMARK: &str = "/Record.Remark";
+
+/// How long to stop calling the API after DNSPod reports that the usage limit was exceeded.
+pub(crate) const RATE_LIMITED_COOLDOWN: Duration = Duration::from_secs(10 * 60);
+/// How long to stop calling the API after DNSPod rejects too frequent modifications.
+pub(crate) const FREQUENT_MODIFICATION_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+/// How many domains are requested per `Domain.List` page.
+pub(crate) const DOMAIN_PAGE_SIZE: usize = 500;
+
+/// How many times an idempotent API call is attempted before giving up.
+pub(crate) const MAX_ATTEMPTS: u32 = 4;
+/// The backoff cap of the first retry; doubled for every further attempt.
+pub(crate) const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// The largest backoff cap between two attempts.
+pub(crate) const RETRY_MAX_DELAY: Duration = Duration::from_secs(8);
@@ -1,12 +1,31 @@
 use super::constants::*;
+use super::duplicates::DuplicateStrategy;
+use super::limiter::RateLimiter;
+use super::name::{display_name, normalize_name, split_name};
+use super::outage::OutageAction;
+use super::outcome::UpdateOutcome;
+use super::owner::Ownership;
+use super::persist::{
+    FamilyEntry, PendingEntry, RecordEntry, StateStore, TargetEntry, ZoneEntry, age_of,
+    unix_secs_ago,
+};
+use super::query::{RecordPage, RecordQuery};
+use super::record::{Record, RecordSpec, RecordType};
+use super::shutdown::ShutdownAction;
+use super::stability::{FlapGuard, StabilityPolicy};
+use super::status::ApiCode;
 use crate::error::{DdnsError, Result};
-use crate::utils::Id;
+use crate::policy::AddressPolicy;
+use crate::utils::{Id, lenient_number};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tracing::{info, instrument, trace, warn};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
+use tokio::time;
+use tracing::{Span, debug, info, instrument, trace, warn};
 
 // --- API Response Models ---
 #[derive(Deserialize, Debug)]
@@ -15,52 +34,188 @@ struct Status {
     message: String,
 }
 
+impl Status {
+    /// Converts a non-success status of `endpoint` into `DdnsError::Api` with a typed code.
+    fn into_result(self, endpoint: &str) -> Result<()> {
+        let code = ApiCode::parse(endpoint, &self.code, &self.message);
+        if code.is_success() {
+            return Ok(());
+        }
+        Err(DdnsError::Api {
+            code,
+            raw_code: self.code,
+            message: self.message,
+        })
+    }
+}
+
 // Record.List
 #[derive(Deserialize, Debug)]
 struct ListResponse {
     status: Status,
-    records: Option<Vec<ListRecord>>,
+    info: Option<ListInfo>,
+    records: Option<Vec<Record>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ListInfo {
+    #[serde(default, deserialize_with = "lenient_number")]
+    record_total: usize,
 }
 
 //  Record.Create
 #[derive(Deserialize, Debug)]
 struct CreateResponse {
     status: Status,
-    record: CreatedRecord,
+    record: Option<CreatedRecord>,
 }
 
-//  Record.Modify
+//  Record.Modify, Record.Status, Record.Remark, Record.Remove
 #[derive(Deserialize, Debug)]
 struct ModifyResponse {
     status: Status,
 }
 
-#[derive(Deserialize, Clone, Debug)]
-pub struct ListRecord {
-    pub id: Id,
-    pub value: String,
-    #[serde(rename = "type")]
-    pub record_type: String,
+// Record.Info
+#[derive(Deserialize, Debug)]
+struct InfoResponse {
+    status: Status,
+    record: Option<Record>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CreatedRecord {
+    id: Id,
+}
+
+// Domain.List
+#[derive(Deserialize, Debug)]
+struct DomainListResponse {
+    status: Status,
+    info: Option<DomainListInfo>,
+    domains: Option<Vec<DomainEntry>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DomainListInfo {
+    #[serde(default, deserialize_with = "lenient_number")]
+    domain_total: usize,
 }
 
 #[derive(Deserialize, Debug)]
-pub struct CreatedRecord {
-    pub id: Id,
-    pub name: String,
-    pub status: String,
+struct DomainEntry {
+    name: String,
 }
 
 // --- Internal State Management ---
+/// The DNSPod domain (zone) and the sub-domain within it that a client manages.
+#[derive(Debug)]
+struct Zone {
+    domain: String,
+    sub_domain: String,
+}
+
 #[derive(Default, Debug, Clone)]
 struct RecordState {
     id: String,
     ip: String,
+    enabled: bool,
+    remark: String,
 }
 
+#[derive(Default, Debug, Clone)]
+struct FamilyState {
+    /// All records of this type on the managed line, primary record first.
+    records: Vec<RecordState>,
+    guard: FlapGuard,
+    /// When this family was first reported gone.
+    missing_since: Option<Instant>,
+    /// The address we had published before reconciliation found the records changed.
+    drift_from: Option<String>,
+    /// Records that reconciliation found disabled out of band, to be enabled again.
+    disabled_out_of_band: Vec<String>,
+    /// Set while the primary record still points at the parking address of a shutdown.
+    parked: bool,
+    /// Set while the records deleted by a shutdown have not been created again.
+    released: bool,
+    /// When the records of this family were last read from DNSPod.
+    last_refresh: Option<Instant>,
+    /// Bumped on every commit, so a reconciliation can tell that a writer came first.
+    version: u64,
+}
+
+/// The cached records of one address family.
+///
+/// `state` is only ever locked for short synchronous sections, never across an API call.
+/// Writers (updates, outage actions, shutdown) are serialized by `writer` instead: each
+/// works on a snapshot of the state without holding `state` and commits the result.
+#[derive(Default, Debug)]
+struct FamilyCell {
+    state: std::sync::Mutex<FamilyState>,
+    writer: Mutex<()>,
+    /// What was last written to the state file for this family. Held while writing it.
+    saved: Mutex<Option<FamilyEntry>>,
+}
+
+impl FamilyCell {
+    /// (Private) Locks the state for a short synchronous section.
+    fn lock(&self) -> MutexGuard<'_, FamilyState> {
+        self.state.lock().expect("record state lock poisoned")
+    }
+
+    /// (Private) A working copy of the state for a writer holding `writer`.
+    fn snapshot(&self) -> FamilyState {
+        self.lock().clone()
+    }
+
+    /// (Private) Replaces the state with a writer's working copy.
+    ///
+    /// Only writers holding `writer` and reconciliations that checked the version under
+    /// it commit once the client is initialized, so the working copy is never stale.
+    ///
+    /// In a dry run the writes were skipped, so the records are kept as DNSPod has them;
+    /// otherwise a reconciliation would report the skipped writes as out-of-band changes.
+    /// Flap protection is rolled back too if a skipped write was recorded as published, so
+    /// the minimum update interval does not hold back the next cycle.
+    fn commit(&self, mut working: FamilyState, dry_run: bool) {
+        let mut state = self.lock();
+        working.version = state.version + 1;
+        if dry_run {
+            if working.guard.published_since(&state.guard) {
+                working.guard = state.guard.clone();
+            }
+            working.records = std::mem::take(&mut state.records);
+            working.parked = state.parked;
+            working.released = state.released;
+        }
+        *state = working;
+    }
+}
+
+/// The cached records, kept per address family so that the two families are updated
+/// independently.
 #[derive(Default, Debug)]
 struct DdnsState {
-    a: Option<RecordState>,
-    aaaa: Option<RecordState>,
+    a: FamilyCell,
+    aaaa: FamilyCell,
+    /// Set while a reconciliation runs, so only one runs at a time.
+    reconciling: AtomicBool,
+}
+
+impl DdnsState {
+    /// The state of one address family.
+    fn family(&self, is_ipv4: bool) -> &FamilyCell {
+        if is_ipv4 { &self.a } else { &self.aaaa }
+    }
+}
+
+/// The records a strategy manages: all of them, or only the primary record.
+fn managed(records: &mut [RecordState], strategy: DuplicateStrategy) -> &mut [RecordState] {
+    let count = match strategy {
+        DuplicateStrategy::All => records.len(),
+        _ => records.len().min(1),
+    };
+    &mut records[..count]
 }
 
 /// An asynchronous, stateful client for the DNSPod DDNS service.
@@ -68,221 +223,1842 @@ struct DdnsState {
 pub struct DnspodClient {
     client: reqwest::Client,
     token: String,
-    domain: String,
-    sub_domain: String,
-    state: Arc<Mutex<DdnsState>>,
+    /// The full name of the managed records in ASCII, e.g. "home.xn--fsqu00a.xn--fiqs8s".
+    fqdn: String,
+    /// The same name in Unicode for logs, e.g. "home.例子.中国".
+    display_name: String,
+    /// The zone and sub-domain; discovered by `initialize` for clients from `new_for_name`.
+    zone: Arc<OnceLock<Zone>>,
+    record_line: String,
+    policy: AddressPolicy,
+    stability: StabilityPolicy,
+    outage_action: OutageAction,
+    outage_grace: Duration,
+    shutdown_action: ShutdownAction,
+    park_a: Option<IpAddr>,
+    park_aaaa: Option<IpAddr>,
+    ownership: Option<Ownership>,
+    /// Records created by this client whose ownership marker could not be set yet.
+    unmarked: Arc<std::sync::Mutex<HashSet<String>>>,
+    replace_conflicting: bool,
+    duplicates: DuplicateStrategy,
+    pinned: Vec<String>,
+    reconcile_interval: Duration,
+    store: Option<Arc<StateStore>>,
+    dry_run: bool,
+    skipped: Arc<AtomicU64>,
+    limiter: Arc<RateLimiter>,
+    /// Set once the records of the sub-domain were fetched for the first time.
+    initialized: Arc<OnceCell<()>>,
+    state: Arc<DdnsState>,
 }
 
 impl DnspodClient {
-    /// Initializes the client and fetches the initial state from DNSPod.
+    /// Creates a client for the default line.
+    ///
+    /// No API call is made; the records are fetched on first use or by `initialize`.
+    pub fn new(token: String, domain: String, sub_domain: String) -> Result<Self> {
+        Self::new_for_line(token, domain, sub_domain, DEFAULT_RECORD_LINE.to_string())
+    }
+
+    /// Creates a client for a specific DNSPod record line (e.g. "电信" or "联通").
+    /// Only records on that line are managed.
+    ///
+    /// No API call is made; the records are fetched on first use or by `initialize`.
+    #[instrument(skip(token))]
+    pub fn new_for_line(
+        token: String,
+        domain: String,
+        sub_domain: String,
+        record_line: String,
+    ) -> Result<Self> {
+        let domain = normalize_name(&domain)?;
+        let name = normalize_name(&format!("{}.{}", sub_domain, domain))?;
+        let (domain, sub_domain) = split_name(&name, &[domain])
+            .ok_or_else(|| DdnsError::ZoneNotFound(display_name(&name)))?;
+        info!(
+            "👋 Creating DNSPod client for [{}] on line '{}'",
+            display_name(&name),
+            record_line
+        );
+        let zone = OnceLock::new();
+        let _ = zone.set(Zone { domain, sub_domain });
+        Self::build(token, name, zone, record_line)
+    }
+
+    /// Creates a client for a fully qualified name such as "home.example.com.cn",
+    /// "example.com" (the apex) or "*.example.com", on a specific DNSPod record line.
+    ///
+    /// Unicode names are accepted and sent to DNSPod as punycode.
+    /// The name is validated right away. The zone managing it, the longest matching domain
+    /// of the account, is discovered via `Domain.List` by `initialize`.
     #[instrument(skip(token))]
-    pub async fn new(token: String, domain: String, sub_domain: String) -> Result<Self> {
+    pub fn new_for_name(token: String, name: String, record_line: String) -> Result<Self> {
+        let name = normalize_name(&name)?;
         info!(
-            "👋 Initializing DNSPod client for [{}.{}]",
-            sub_domain, domain
+            "👋 Creating DNSPod client for [{}] on line '{}'",
+            display_name(&name),
+            record_line
         );
+        Self::build(token, name, OnceLock::new(), record_line)
+    }
+
+    /// (Private) Creates a client with default settings.
+    fn build(
+        token: String,
+        name: String,
+        zone: OnceLock<Zone>,
+        record_line: String,
+    ) -> Result<Self> {
         let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(10))
             .build()?;
 
-        let s = Self {
+        Ok(Self {
             client,
             token,
-            domain,
-            sub_domain,
-            state: Arc::new(Mutex::new(DdnsState::default())),
-        };
+            display_name: display_name(&name),
+            fqdn: name,
+            zone: Arc::new(zone),
+            record_line,
+            policy: AddressPolicy::default(),
+            stability: StabilityPolicy::default(),
+            outage_action: OutageAction::Keep,
+            outage_grace: Duration::ZERO,
+            shutdown_action: ShutdownAction::Keep,
+            park_a: None,
+            park_aaaa: None,
+            ownership: None,
+            unmarked: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            replace_conflicting: false,
+            duplicates: DuplicateStrategy::First,
+            pinned: Vec::new(),
+            reconcile_interval: Duration::ZERO,
+            store: None,
+            dry_run: false,
+            skipped: Arc::new(AtomicU64::new(0)),
+            limiter: Arc::new(RateLimiter::default()),
+            initialized: Arc::new(OnceCell::new()),
+            state: Arc::new(DdnsState::default()),
+        })
+    }
 
-        // Fetch initial state to populate record IDs and IPs.
-        s.refresh_state().await?;
+    /// Fetches the records of the sub-domain unless that already succeeded.
+    ///
+    /// Safe to call repeatedly: after a failure the next call tries again, and concurrent
+    /// callers share a single attempt. Permanent failures such as a bad token or an
+    /// unknown domain are classified as `ErrorClass::Configuration`.
+    pub async fn initialize(&self) -> Result<()> {
+        self.initialized
+            .get_or_try_init(|| async {
+                let persisted = self.load_persisted().await;
+                let cached_zone = persisted
+                    .as_ref()
+                    .filter(|(_, trusted)| *trusted)
+                    .and_then(|(entry, _)| entry.zone.clone());
+                self.resolve_zone(cached_zone).await?;
+                if !self.restore_persisted(persisted).await {
+                    self.refresh_state().await?;
+                    for is_ipv4 in [true, false] {
+                        self.persist(is_ipv4).await;
+                    }
+                }
+                self.detect_parked();
+                Ok(())
+            })
+            .await
+            .map(|_| ())
+    }
 
-        Ok(s)
+    /// (Private) Discovers the zone that manages the name, unless it is known already or
+    /// `cached` in a trusted state file entry.
+    async fn resolve_zone(&self, cached: Option<ZoneEntry>) -> Result<()> {
+        if self.zone.get().is_some() {
+            return Ok(());
+        }
+        if let Some(ZoneEntry { domain, sub_domain }) = cached {
+            debug!(
+                "[{}] Using the cached zone '{}'.",
+                self.display_name,
+                display_name(&domain)
+            );
+            let _ = self.zone.set(Zone { domain, sub_domain });
+            return Ok(());
+        }
+        let zones = self.list_domains().await?;
+        let (domain, sub_domain) = split_name(&self.fqdn, &zones)
+            .ok_or_else(|| DdnsError::ZoneNotFound(self.display_name.clone()))?;
+        info!(
+            "🧭 [{}] Managed as '{}' in zone '{}'",
+            self.display_name,
+            display_name(&sub_domain),
+            display_name(&domain)
+        );
+        let _ = self.zone.set(Zone { domain, sub_domain });
+        Ok(())
+    }
+
+    /// The full name of the managed records, in Unicode.
+    pub fn name(&self) -> &str {
+        &self.display_name
+    }
+
+    /// (Private) The managed zone; empty until it was discovered.
+    fn domain(&self) -> &str {
+        self.zone.get().map_or("", |zone| zone.domain.as_str())
+    }
+
+    /// (Private) The managed sub-domain within the zone; empty until it was discovered.
+    fn sub_domain(&self) -> &str {
+        self.zone.get().map_or("", |zone| zone.sub_domain.as_str())
+    }
+
+    /// Whether the records of the sub-domain have been fetched yet.
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.initialized()
+    }
+
+    /// Replaces the policy that every detected address must pass before it is published.
+    pub fn with_policy(mut self, policy: AddressPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the stability window and minimum modification interval used for flap protection.
+    pub fn with_stability(mut self, stability: StabilityPolicy) -> Self {
+        self.stability = stability;
+        self
+    }
+
+    /// Sets what happens to a record once its address family has been gone for `grace`.
+    pub fn with_outage_action(mut self, action: OutageAction, grace: Duration) -> Self {
+        self.outage_action = action;
+        self.outage_grace = grace;
+        self
+    }
+
+    /// Sets what happens to the managed records when `shutdown` is called.
+    pub fn with_shutdown_action(mut self, action: ShutdownAction) -> Self {
+        self.shutdown_action = action;
+        self
+    }
+
+    /// Sets the address that records of its family point to while parked by
+    /// `ShutdownAction::Park`. It is not checked against the address policy.
+    pub fn with_parking_address(mut self, ip: IpAddr) -> Self {
+        if ip.is_ipv4() {
+            self.park_a = Some(ip);
+        } else {
+            self.park_aaaa = Some(ip);
+        }
+        self
+    }
+
+    /// Tags created records with an ownership marker and refuses to modify, disable or
+    /// delete records that lack it, unless the ownership policy adopts foreign records.
+    pub fn with_ownership(mut self, ownership: Ownership) -> Self {
+        self.ownership = Some(ownership);
+        self
+    }
+
+    /// Deletes CNAME and URL forwarding records at the name instead of failing with
+    /// `DdnsError::ConflictingRecord` when the records are loaded or a create conflicts.
+    /// NS delegations are never replaced, nor are records of other owners unless foreign
+    /// records are adopted.
+    pub fn with_replace_conflicting(mut self, replace: bool) -> Self {
+        self.replace_conflicting = replace;
+        self
+    }
+
+    /// Sets how several records of the same type under the sub-domain are handled.
+    pub fn with_duplicate_strategy(mut self, strategy: DuplicateStrategy) -> Self {
+        self.duplicates = strategy;
+        self
+    }
+
+    /// Pins record IDs that are preferred as the primary record over the lowest ID.
+    pub fn with_pinned_records(mut self, record_ids: Vec<String>) -> Self {
+        self.pinned = record_ids;
+        self
+    }
+
+    /// Re-reads the records from DNSPod at most every `interval` to detect and correct
+    /// out-of-band changes. `Duration::ZERO` disables reconciliation.
+    pub fn with_reconcile_interval(mut self, interval: Duration) -> Self {
+        self.reconcile_interval = interval;
+        self
+    }
+
+    /// Loads the state from `store` on initialization while it is trusted, and keeps the
+    /// store up to date. Several clients may share one store.
+    pub fn with_state_store(mut self, store: Arc<StateStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Only logs the write requests (create, modify, status, remark, remove) that would be
+    /// sent, with the token redacted. Reads still reach DNSPod; the cached records are kept
+    /// as DNSPod has them, and the state file is not written.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Shares an API rate limiter, e.g. between clients that use the same account.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// The number of detected changes held back by flap protection so far.
+    pub fn skipped_changes(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
     }
 
     /// The main update logic. Checks if the IP has changed and calls the appropriate API.
     #[instrument(skip(self), err, fields(ip = %current_ip))]
-    pub async fn update_if_needed(&self, current_ip: &str) -> Result<bool> {
-        let is_ipv4 = match current_ip.parse::<IpAddr>() {
-            Ok(IpAddr::V4(_)) => true,
-            Ok(IpAddr::V6(_)) => false,
-            Err(_) => return Err(DdnsError::InvalidIpFormat(current_ip.to_string())),
+    pub async fn update_if_needed(&self, current_ip: &str) -> Result<UpdateOutcome> {
+        let ip = current_ip
+            .parse::<IpAddr>()
+            .map_err(|_| DdnsError::InvalidIpFormat(current_ip.to_string()))?;
+        if let Err(e) = self.policy.check(&ip) {
+            warn!("⛔ Refusing to publish '{}': {}", current_ip, e);
+            return Err(e);
+        }
+        let is_ipv4 = ip.is_ipv4();
+        self.initialize().await?;
+        self.reconcile_if_due().await?;
+
+        // Updates of this family are serialized; the other family is not held up.
+        let cell = self.state.family(is_ipv4);
+        let _writer = cell.writer.lock().await;
+        let mut family = cell.snapshot();
+        let outcome = self.apply_update(is_ipv4, &mut family, current_ip).await;
+        // A parked record stays parked until an address was actually published.
+        if matches!(&outcome, Ok(outcome) if !matches!(outcome, UpdateOutcome::Skipped(_))) {
+            family.parked = false;
+            family.released = false;
+        }
+        cell.commit(family, self.dry_run);
+        self.persist(is_ipv4).await;
+        outcome
+    }
+
+    /// (Private) Implements `update_if_needed` on a working copy of one family's state.
+    async fn apply_update(
+        &self,
+        is_ipv4: bool,
+        family: &mut FamilyState,
+        current_ip: &str,
+    ) -> Result<UpdateOutcome> {
+        let record_type = &RecordType::for_family(is_ipv4);
+        family.missing_since = None;
+        let drift_from = family.drift_from.take();
+        let disabled_out_of_band = std::mem::take(&mut family.disabled_out_of_band);
+        let parked = family.parked;
+        let released = family.released;
+        if self.duplicates == DuplicateStrategy::Collapse {
+            self.collapse_duplicates(record_type, &mut family.records)
+                .await?;
+        }
+        let records = managed(&mut family.records, self.duplicates);
+        let guard = &mut family.guard;
+
+        // A record paused during an outage is re-enabled as soon as an address is back,
+        // and one disabled out of band is corrected like a changed value.
+        let mut reenabled = false;
+        let mut corrected = drift_from.is_some();
+        for cached_state in records.iter_mut().filter(|r| !r.enabled) {
+            let out_of_band = disabled_out_of_band.contains(&cached_state.id);
+            if !out_of_band && self.outage_action != OutageAction::Disable {
+                continue;
+            }
+            self.ensure_owned(record_type, cached_state).await?;
+            if out_of_band {
+                info!(
+                    "🩹 [{}] Re-enabling record {} after it was disabled out of band...",
+                    record_type, cached_state.id
+                );
+            } else {
+                info!(
+                    "▶️ [{}] Address is back. Re-enabling record {}...",
+                    record_type, cached_state.id
+                );
+            }
+            self.set_record_status(&cached_state.id, true).await?;
+            cached_state.enabled = true;
+            corrected |= out_of_band;
+            reenabled = true;
+        }
+        let unless_reenabled = |outcome| {
+            if corrected && reenabled {
+                UpdateOutcome::Corrected
+            } else if reenabled {
+                UpdateOutcome::Reenabled
+            } else {
+                outcome
+            }
         };
-        let record_type = if is_ipv4 { "A" } else { "AAAA" };
 
-        // Lock the state for the duration of this check.
-        let mut state = self.state.lock().await;
-        let record_state_opt = if is_ipv4 {
-            &mut state.a
-        } else {
-            &mut state.aaaa
+        // Unchanged addresses need no API call; changed ones must pass flap protection first.
+        let unchanged = records.first().is_some_and(|r| r.ip == current_ip);
+        if unchanged {
+            guard.settle();
+            if records.iter().all(|r| r.ip == current_ip) {
+                trace!(
+                    "✅ [{}] IP has not changed from '{}'. No update needed.",
+                    record_type, current_ip
+                );
+                return Ok(unless_reenabled(UpdateOutcome::Unchanged));
+            }
+            // Duplicates managed alongside the primary record still point elsewhere.
+            info!(
+                "👯 [{}] Bringing duplicate records in line with '{}'...",
+                record_type, current_ip
+            );
+        } else if parked {
+            // Coming back from a parked shutdown is not a new address either.
+            info!(
+                "🅿️ [{}] Restoring '{}' after the record was parked at shutdown...",
+                record_type, current_ip
+            );
+        } else if released {
+            // Neither is re-creating the records deleted at shutdown.
+            info!(
+                "♻️ [{}] Restoring '{}' after the records were deleted at shutdown...",
+                record_type, current_ip
+            );
+        } else if drift_from.as_deref() == Some(current_ip) {
+            // Restoring what we had published is not a new address; skip flap protection.
+            info!(
+                "🩹 [{}] Restoring '{}' after an out-of-band change...",
+                record_type, current_ip
+            );
+        } else if let Err(reason) = guard.observe(current_ip, !records.is_empty(), &self.stability)
+        {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            info!(
+                "⏸️ [{}] Holding back change to '{}': {}",
+                record_type, current_ip, reason
+            );
+            return Ok(unless_reenabled(UpdateOutcome::Skipped(reason)));
+        }
+
+        if records.is_empty() {
+            // No cached state, means no record exists. Create it.
+            info!(
+                "✨ No {} No existing record found. Creating new record with IP '{}'...",
+                record_type, current_ip
+            );
+            self.create_and_cache(record_type, family, current_ip)
+                .await?;
+            return Ok(if corrected {
+                UpdateOutcome::Corrected
+            } else {
+                UpdateOutcome::Created
+            });
+        }
+
+        match self
+            .apply_to_records(record_type, records, current_ip)
+            .await
+        {
+            Ok(()) => {
+                if !unchanged {
+                    guard.published();
+                }
+                Ok(if corrected {
+                    UpdateOutcome::Corrected
+                } else {
+                    UpdateOutcome::Updated
+                })
+            }
+            Err(DdnsError::Api {
+                code: ApiCode::RecordIdInvalid,
+                ..
+            }) => {
+                // "Record ID Error (8)"! Our state is stale.
+                warn!("❗️Record ID is outdated. Refreshing state and retrying...");
+                self.refresh_family(record_type, family).await?;
+                self.reapply_after_refresh(record_type, family, current_ip)
+                    .await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// (Private) Points every record that does not have `current_ip` yet at it.
+    async fn apply_to_records(
+        &self,
+        record_type: &RecordType,
+        records: &mut [RecordState],
+        current_ip: &str,
+    ) -> Result<()> {
+        for cached_state in records.iter_mut().filter(|r| r.ip != current_ip) {
+            self.ensure_owned(record_type, cached_state).await?;
+            info!(
+                "🔄 [{}] IP has changed from '{}' to '{}'. Updating record {}...",
+                record_type, cached_state.ip, current_ip, cached_state.id
+            );
+            self.modify_value(record_type, &cached_state.id, current_ip)
+                .await?;
+            // Update successful, update cached IP.
+            cached_state.ip = current_ip.to_string();
+        }
+        Ok(())
+    }
+
+    /// (Private) Deletes every record but the primary one.
+    async fn collapse_duplicates(
+        &self,
+        record_type: &RecordType,
+        records: &mut Vec<RecordState>,
+    ) -> Result<()> {
+        if records.len() < 2 {
+            return Ok(());
+        }
+        warn!(
+            "🧹 [{}] Collapsing {} duplicate records into record {}...",
+            record_type,
+            records.len() - 1,
+            records[0].id
+        );
+        for extra in records.iter_mut().skip(1) {
+            self.ensure_owned(record_type, extra).await?;
+            self.delete_record(&extra.id).await?;
+            info!(
+                "🗑️ [{}] Removed duplicate record {}.",
+                record_type, extra.id
+            );
+        }
+        records.truncate(1);
+        Ok(())
+    }
+
+    /// (Private) Applies `current_ip` once more after the state was refreshed
+    /// following a stale record ID. This is the only retry; errors are returned as-is.
+    async fn reapply_after_refresh(
+        &self,
+        record_type: &RecordType,
+        family: &mut FamilyState,
+        current_ip: &str,
+    ) -> Result<UpdateOutcome> {
+        if self.duplicates == DuplicateStrategy::Collapse {
+            self.collapse_duplicates(record_type, &mut family.records)
+                .await?;
+        }
+        let records = managed(&mut family.records, self.duplicates);
+
+        if records.is_empty() {
+            warn!(
+                "❗️ [{}] Record was deleted out of band. Recreating it...",
+                record_type
+            );
+            self.create_and_cache(record_type, family, current_ip)
+                .await?;
+            return Ok(UpdateOutcome::Created);
+        }
+        if records.iter().all(|r| r.ip == current_ip) {
+            info!(
+                "✅ [{}] Record {} already points to '{}' after refresh.",
+                record_type, records[0].id, current_ip
+            );
+            family.guard.settle();
+            return Ok(UpdateOutcome::Unchanged);
+        }
+
+        info!(
+            "🔁 [{}] Retrying update with refreshed record ID {}...",
+            record_type, records[0].id
+        );
+        self.apply_to_records(record_type, records, current_ip)
+            .await?;
+        family.guard.published();
+        Ok(UpdateOutcome::Updated)
+    }
+
+    /// (Private) Creates a record for `current_ip` and caches it.
+    async fn create_and_cache(
+        &self,
+        record_type: &RecordType,
+        family: &mut FamilyState,
+        current_ip: &str,
+    ) -> Result<()> {
+        let spec = RecordSpec::new(self.sub_domain(), record_type.clone(), current_ip)
+            .with_line(self.record_line.as_str());
+        let created_record = match self.create_record(&spec).await {
+            Err(DdnsError::Api {
+                code: ApiCode::RecordConflict,
+                ..
+            }) => {
+                // Records loaded from the state file were never checked for conflicts.
+                warn!(
+                    "❗️ [{}] DNSPod reports a conflicting record. Checking the records of '{}'...",
+                    record_type, self.display_name
+                );
+                self.list_managed_records().await?;
+                self.create_record(&spec).await?
+            }
+            other => other?,
         };
+        info!(
+            "✅ Successfully created new record. ID: {}, Name: {}",
+            created_record.id, self.display_name
+        );
+        let record_id = created_record.id;
+        let mut remark = String::new();
+        if let Some(ownership) = &self.ownership {
+            // The record exists either way; without the marker it looks foreign after a
+            // refresh, so remember that it is ours and mark it on its next write.
+            let marker = ownership.marker();
+            match self.set_record_remark(&record_id, &marker).await {
+                Ok(()) => remark = marker,
+                Err(e) => {
+                    warn!(
+                        "❗️ [{}] Could not mark record {} as managed, retrying on its next write: {}",
+                        record_type, record_id, e
+                    );
+                    self.unmarked_records().insert(record_id.clone());
+                }
+            }
+        }
+        family.records = vec![RecordState {
+            id: record_id,
+            ip: current_ip.to_string(),
+            enabled: true,
+            remark,
+        }];
+        family.guard.published();
+        Ok(())
+    }
 
-        match record_state_opt {
-            Some(cached_state) => {
-                // We have a cached state (ID and IP).
-                if cached_state.ip == current_ip {
-                    trace!(
-                        "✅ [{}] IP has not changed from '{}'. No update needed.",
-                        record_type, cached_state.ip
+    /// Reports that the host has no address of the given family any more, e.g. because
+    /// there is no route for it. Callers should not report a merely failing IP source.
+    ///
+    /// Once the family has been gone for longer than the configured grace period, the
+    /// record is disabled or deleted according to the outage action. Returns true if it was.
+    #[instrument(skip(self))]
+    pub async fn handle_missing_address(&self, is_ipv4: bool) -> Result<bool> {
+        if self.outage_action == OutageAction::Keep {
+            return Ok(false);
+        }
+        self.initialize().await?;
+
+        let cell = self.state.family(is_ipv4);
+        let _writer = cell.writer.lock().await;
+        let mut family = cell.snapshot();
+        let handled = self.apply_outage_action(is_ipv4, &mut family).await;
+        cell.commit(family, self.dry_run);
+        self.persist(is_ipv4).await;
+        handled
+    }
+
+    /// (Private) Implements `handle_missing_address` on a working copy of one family's state.
+    async fn apply_outage_action(&self, is_ipv4: bool, family: &mut FamilyState) -> Result<bool> {
+        let record_type = &RecordType::for_family(is_ipv4);
+        let since = *family.missing_since.get_or_insert_with(Instant::now);
+        if since.elapsed() < self.outage_grace {
+            trace!(
+                "[{}] Address missing for {:?}; within the grace period.",
+                record_type,
+                since.elapsed()
+            );
+            return Ok(false);
+        }
+
+        let records = managed(&mut family.records, self.duplicates);
+        if records.is_empty() {
+            return Ok(false);
+        }
+        match self.outage_action {
+            OutageAction::Disable => {
+                let mut changed = false;
+                for cached_state in records.iter_mut().filter(|r| r.enabled) {
+                    self.ensure_owned(record_type, cached_state).await?;
+                    warn!(
+                        "⏯️ [{}] No address detected for {:?}. Disabling record {}...",
+                        record_type,
+                        since.elapsed(),
+                        cached_state.id
                     );
-                    return Ok(false);
+                    self.set_record_status(&cached_state.id, false).await?;
+                    cached_state.enabled = false;
+                    changed = true;
                 }
-                info!(
-                    "🔄 [{}] IP has changed from '{}' to '{}'. Updating record...",
-                    record_type, cached_state.ip, current_ip
-                );
-                match self
-                    .modify_record(record_type, &cached_state.id, current_ip)
-                    .await
-                {
-                    Ok(_) => {
-                        // Update successful, update cached IP.
-                        cached_state.ip = current_ip.to_string();
+                Ok(changed)
+            }
+            OutageAction::Delete => {
+                let count = records.len();
+                for cached_state in records.iter_mut() {
+                    self.ensure_owned(record_type, cached_state).await?;
+                    warn!(
+                        "🗑️ [{}] No address detected for {:?}. Deleting record {}...",
+                        record_type,
+                        since.elapsed(),
+                        cached_state.id
+                    );
+                    self.delete_record(&cached_state.id).await?;
+                }
+                family.records.drain(..count);
+                Ok(true)
+            }
+            OutageAction::Keep => Ok(false),
+        }
+    }
+
+    /// Deletes the managed records or points them at their parking address, according to
+    /// the shutdown action. Meant to be called once before the process exits; the records
+    /// are restored by the next `update_if_needed`. Returns the number of records changed.
+    ///
+    /// Every record is attempted even if some fail; the failures are then reported
+    /// together as `DdnsError::ReleaseFailed`.
+    #[instrument(skip(self))]
+    pub async fn shutdown(&self) -> Result<usize> {
+        if self.shutdown_action == ShutdownAction::Keep || !self.is_initialized() {
+            return Ok(0);
+        }
+        let mut released = 0;
+        let mut errors = Vec::new();
+        for is_ipv4 in [true, false] {
+            let cell = self.state.family(is_ipv4);
+            let _writer = cell.writer.lock().await;
+            let mut family = cell.snapshot();
+            released += self.release_family(is_ipv4, &mut family, &mut errors).await;
+            cell.commit(family, self.dry_run);
+            self.persist(is_ipv4).await;
+        }
+        if errors.is_empty() {
+            return Ok(released);
+        }
+        Err(DdnsError::ReleaseFailed {
+            released,
+            failed: errors.len(),
+            source: Box::new(errors.swap_remove(0)),
+        })
+    }
+
+    /// (Private) Implements `shutdown` on a working copy of one family's state. Returns
+    /// the number of records released and adds the failures to `errors`.
+    async fn release_family(
+        &self,
+        is_ipv4: bool,
+        family: &mut FamilyState,
+        errors: &mut Vec<DdnsError>,
+    ) -> usize {
+        let record_type = &RecordType::for_family(is_ipv4);
+        let records = managed(&mut family.records, self.duplicates);
+        let mut released = Vec::new();
+        match self.shutdown_action {
+            ShutdownAction::Delete => {
+                for cached_state in records.iter_mut() {
+                    let result = async {
+                        self.ensure_owned(record_type, cached_state).await?;
+                        info!(
+                            "🗑️ [{}] Shutting down. Deleting record {}...",
+                            record_type, cached_state.id
+                        );
+                        self.delete_record(&cached_state.id).await
                     }
-                    Err(DdnsError::Api { code, .. }) if code == "8" => {
-                        // "Record ID Error (8)"! Our state is stale.
-                        warn!("❗️Record ID is outdated. Refreshing state and retrying...");
-                        // Drop the lock before calling another method on `self` to avoid deadlock.
-                        drop(state);
-                        self.refresh_state().await?;
-                        // After refreshing, the state might be correct now.
-                        // The next tick of the loop will handle the update.
-                        // Or we could retry immediately here. For simplicity, we'll let the next tick handle it.
-                        return Err(DdnsError::Api {
-                            code: "8".to_string(),
-                            message:
-                                "State refreshed after Record ID error. The next cycle will retry."
-                                    .to_string(),
-                        });
+                    .await;
+                    match result {
+                        Ok(()) => released.push(cached_state.id.clone()),
+                        Err(e) => {
+                            warn!(
+                                "🚨 [{}] Could not delete record {}: {}",
+                                record_type, cached_state.id, e
+                            );
+                            errors.push(e);
+                        }
                     }
-                    Err(e) => return Err(e),
                 }
+                family.records.retain(|r| !released.contains(&r.id));
+                family.released |= !released.is_empty();
             }
-            None => {
-                // No cached state, means no record exists. Create it.
+            ShutdownAction::Park => {
+                let Some(park_ip) = self.parking_address(is_ipv4) else {
+                    return 0;
+                };
+                for cached_state in records.iter_mut().filter(|r| r.ip != park_ip) {
+                    let result = async {
+                        self.ensure_owned(record_type, cached_state).await?;
+                        info!(
+                            "🅿️ [{}] Shutting down. Parking record {} at '{}'...",
+                            record_type, cached_state.id, park_ip
+                        );
+                        self.modify_value(record_type, &cached_state.id, &park_ip)
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(()) => {
+                            cached_state.ip = park_ip.clone();
+                            released.push(cached_state.id.clone());
+                        }
+                        Err(e) => {
+                            warn!(
+                                "🚨 [{}] Could not park record {}: {}",
+                                record_type, cached_state.id, e
+                            );
+                            errors.push(e);
+                        }
+                    }
+                }
+            }
+            ShutdownAction::Keep => {}
+        }
+        released.len()
+    }
+
+    /// (Private) The parking address of a family, if one is configured.
+    fn parking_address(&self, is_ipv4: bool) -> Option<String> {
+        let ip = if is_ipv4 { self.park_a } else { self.park_aaaa };
+        ip.map(|ip| ip.to_string())
+    }
+
+    /// (Private) Notes which families still point at their parking address, so they are
+    /// restored right away instead of passing flap protection first.
+    fn detect_parked(&self) {
+        if self.shutdown_action != ShutdownAction::Park {
+            return;
+        }
+        for is_ipv4 in [true, false] {
+            let Some(park_ip) = self.parking_address(is_ipv4) else {
+                continue;
+            };
+            let mut family = self.state.family(is_ipv4).lock();
+            family.parked = family.records.first().is_some_and(|r| r.ip == park_ip);
+            if family.parked {
                 info!(
-                    "✨ No {} No existing record found. Creating new record with IP '{}'...",
-                    record_type, current_ip
+                    "🅿️ [{}] Record {} is parked at '{}'; it is restored with the next detected address.",
+                    RecordType::for_family(is_ipv4),
+                    family.records[0].id,
+                    park_ip
                 );
-                drop(state); // Drop lock before async operation
-                let created_record = self.create_record(record_type, current_ip).await?;
-                info!(
-                    "✅ Successfully created new record. ID: {}, Name: {}, Status: {}",
-                    created_record.id, created_record.name, created_record.status
+            }
+        }
+    }
+
+    /// Re-reads the records from DNSPod and reports changes made out of band since the
+    /// last read, e.g. in the DNSPod console. Returns the number of changed records.
+    ///
+    /// Both families are filled from a single listing. A family that is being updated
+    /// meanwhile is left alone and reconciled next time, since the listing may predate
+    /// that update. Changed values are corrected by the next `update_if_needed`.
+    #[instrument(skip(self))]
+    pub async fn reconcile(&self) -> Result<usize> {
+        let versions = [true, false].map(|is_ipv4| self.state.family(is_ipv4).lock().version);
+        // Conflicts are only resolved when the records are (re)loaded, never in passing.
+        let listed = self.list_sub_domain_records().await?;
+        let mut drifted = 0;
+        for (is_ipv4, version) in [true, false].into_iter().zip(versions) {
+            let record_type = &RecordType::for_family(is_ipv4);
+            let cell = self.state.family(is_ipv4);
+            // Never wait for a writer's API calls; its records are checked next time.
+            let Ok(_writer) = cell.writer.try_lock() else {
+                debug!(
+                    "[{}] Records are being updated; reconciling them next time.",
+                    record_type
                 );
-                let mut state = self.state.lock().await; // Re-acquire lock
-                let new_state = RecordState {
-                    id: created_record.id.to_string(),
-                    ip: current_ip.to_string(),
-                };
-                if is_ipv4 {
-                    state.a = Some(new_state);
-                } else {
-                    state.aaaa = Some(new_state);
+                continue;
+            };
+            {
+                let mut family = cell.lock();
+                if family.version != version {
+                    debug!(
+                        "[{}] Records changed while they were listed; reconciling them next time.",
+                        record_type
+                    );
+                    continue;
                 }
+                drifted += self.reconcile_family(record_type, &mut family, &listed);
+                family.version += 1;
             }
+            self.persist(is_ipv4).await;
         }
-        Ok(true)
+        Ok(drifted)
     }
 
-    /// Fetches all records and updates the internal state.
+    /// (Private) Reconciles both families if either is due and no other reconciliation
+    /// is running.
+    async fn reconcile_if_due(&self) -> Result<()> {
+        let due = !self.reconcile_interval.is_zero()
+            && [true, false].into_iter().any(|is_ipv4| {
+                self.state
+                    .family(is_ipv4)
+                    .lock()
+                    .last_refresh
+                    .is_none_or(|at| at.elapsed() >= self.reconcile_interval)
+            });
+        if !due || self.state.reconciling.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let result = self.reconcile().await;
+        self.state.reconciling.store(false, Ordering::Release);
+        result.map(|_| ())
+    }
+
+    /// (Private) Replaces the cached records of one family with the `listed` records and
+    /// reports the differences. Returns the number of changed records.
+    fn reconcile_family(
+        &self,
+        record_type: &RecordType,
+        family: &mut FamilyState,
+        listed: &[Record],
+    ) -> usize {
+        let cached = std::mem::take(&mut family.records);
+        self.fill_family(record_type, family, listed);
+
+        let mut drifted = 0;
+        let mut value_drift = false;
+        for old in &cached {
+            match family.records.iter().find(|r| r.id == old.id) {
+                None => {
+                    warn!(
+                        "🕵️ [{}] Record {} ('{}') was deleted out of band.",
+                        record_type, old.id, old.ip
+                    );
+                    value_drift = true;
+                    drifted += 1;
+                }
+                Some(remote) if remote.ip != old.ip => {
+                    warn!(
+                        "🕵️ [{}] Record {} was changed out of band from '{}' to '{}'.",
+                        record_type, old.id, old.ip, remote.ip
+                    );
+                    value_drift = true;
+                    drifted += 1;
+                }
+                Some(remote) if remote.enabled != old.enabled => {
+                    if !remote.enabled {
+                        family.disabled_out_of_band.push(remote.id.clone());
+                    }
+                    warn!(
+                        "🕵️ [{}] Record {} was {} out of band.",
+                        record_type,
+                        old.id,
+                        if remote.enabled {
+                            "enabled"
+                        } else {
+                            "disabled"
+                        }
+                    );
+                    drifted += 1;
+                }
+                Some(_) => {}
+            }
+        }
+        for new in family
+            .records
+            .iter()
+            .filter(|r| !cached.iter().any(|old| old.id == r.id))
+        {
+            warn!(
+                "🕵️ [{}] Record {} ('{}') appeared out of band.",
+                record_type, new.id, new.ip
+            );
+            drifted += 1;
+        }
+
+        if value_drift {
+            family.drift_from = cached.first().map(|r| r.ip.clone());
+        }
+        if drifted == 0 {
+            debug!("[{}] No out-of-band changes found.", record_type);
+        }
+        drifted
+    }
+
+    /// (Private) The key of this client's entry in the state file. It does not depend on
+    /// the zone, which is only known after the entry was loaded.
+    fn store_key(&self) -> String {
+        format!("{}@{}", self.fqdn, self.record_line)
+    }
+
+    /// (Private) Reads this client's entry from the state file, and whether it is trusted.
+    async fn load_persisted(&self) -> Option<(TargetEntry, bool)> {
+        let (reader, key) = (self.store.clone()?, self.store_key());
+        tokio::task::spawn_blocking(move || reader.load(&key))
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// (Private) Fills the state from a `persisted` entry. Returns false if there is no
+    /// trusted entry and the records must be fetched from DNSPod; the timers are
+    /// restored either way.
+    async fn restore_persisted(&self, persisted: Option<(TargetEntry, bool)>) -> bool {
+        let (Some(store), Some((entry, trusted))) = (&self.store, persisted) else {
+            return false;
+        };
+        self.restore_timers(&entry);
+        if !trusted {
+            return false;
+        }
+
+        let age = age_of(entry.a.refreshed_at.min(entry.aaaa.refreshed_at));
+        let mut found = Vec::new();
+        for (is_ipv4, cached) in [(true, entry.a), (false, entry.aaaa)] {
+            let cell = self.state.family(is_ipv4);
+            let mut saved = cell.saved.lock().await;
+            let mut family = cell.lock();
+            family.records = cached
+                .records
+                .iter()
+                .map(|r| RecordState {
+                    id: r.id.clone(),
+                    ip: r.ip.clone(),
+                    enabled: r.enabled,
+                    remark: r.remark.clone(),
+                })
+                .collect();
+            family.last_refresh = Some(instant_of(cached.refreshed_at));
+            found.push(describe(&family.records));
+            *saved = Some(cached);
+        }
+        info!(
+            "💾 State loaded from {} ({:?} old): A record found ({}), AAAA record found ({})",
+            store.path().display(),
+            age,
+            found[0],
+            found[1]
+        );
+        true
+    }
+
+    /// (Private) Restores the flap protection and outage timers of both families, and
+    /// whether a shutdown deleted their records, which stay meaningful after the cached
+    /// records are no longer trusted.
+    fn restore_timers(&self, entry: &TargetEntry) {
+        for (is_ipv4, cached) in [(true, &entry.a), (false, &entry.aaaa)] {
+            let mut family = self.state.family(is_ipv4).lock();
+            if let Some(published_at) = cached.published_at {
+                family.guard.restore_published(age_of(published_at));
+            }
+            if let Some(pending) = &cached.pending {
+                family
+                    .guard
+                    .restore_pending(&pending.ip, pending.count, age_of(pending.since));
+            }
+            family.missing_since = cached.missing_since.map(instant_of);
+            family.released = cached.released;
+        }
+    }
+
+    /// (Private) Writes the state of one family to the state file if it changed since
+    /// the last write. The file is written on a blocking thread.
+    async fn persist(&self, is_ipv4: bool) {
+        let Some(store) = self.store.clone().filter(|_| !self.dry_run) else {
+            return;
+        };
+        let cell = self.state.family(is_ipv4);
+        // Taken first, so a state built later is never overwritten by an older one.
+        let mut saved = cell.saved.lock().await;
+        let Some(entry) = self.family_entry(&cell.lock()) else {
+            return; // Nothing was loaded yet.
+        };
+        if saved.as_ref() == Some(&entry) {
+            return;
+        }
+        let Some(zone) = self.zone.get() else {
+            return;
+        };
+        let zone = ZoneEntry {
+            domain: zone.domain.clone(),
+            sub_domain: zone.sub_domain.clone(),
+        };
+        let key = self.store_key();
+        let (writer, written) = (store.clone(), entry.clone());
+        let result = tokio::task::spawn_blocking(move || writer.save(&key, zone, is_ipv4, written))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        match result {
+            Ok(()) => *saved = Some(entry),
+            Err(e) => warn!(
+                "❗️ Could not write state file {}: {}",
+                store.path().display(),
+                e
+            ),
+        }
+    }
+
+    /// (Private) The state file entry of one family, once its records were loaded.
+    fn family_entry(&self, family: &FamilyState) -> Option<FamilyEntry> {
+        let last_refresh = family.last_refresh?;
+        Some(FamilyEntry {
+            refreshed_at: unix_secs_ago(last_refresh.elapsed()),
+            records: family
+                .records
+                .iter()
+                .map(|r| RecordEntry {
+                    id: r.id.clone(),
+                    ip: r.ip.clone(),
+                    enabled: r.enabled,
+                    remark: r.remark.clone(),
+                })
+                .collect(),
+            published_at: family.guard.since_published().map(unix_secs_ago),
+            missing_since: family
+                .missing_since
+                .map(|since| unix_secs_ago(since.elapsed())),
+            released: family.released,
+            pending: family
+                .guard
+                .pending()
+                .map(|(ip, count, since)| PendingEntry {
+                    ip: ip.to_string(),
+                    count,
+                    since: unix_secs_ago(since),
+                }),
+        })
+    }
+
+    /// (Private) Locks the IDs of the records created by this client but not marked yet.
+    fn unmarked_records(&self) -> MutexGuard<'_, HashSet<String>> {
+        self.unmarked
+            .lock()
+            .expect("unmarked records lock poisoned")
+    }
+
+    /// (Private) Makes sure a record may be written to. Foreign records are rejected, or
+    /// marked as managed first when the ownership policy adopts them.
+    async fn ensure_owned(&self, record_type: &RecordType, record: &mut RecordState) -> Result<()> {
+        let Some(ownership) = self
+            .ownership
+            .as_ref()
+            .filter(|ownership| !ownership.owns(&record.remark))
+        else {
+            return Ok(());
+        };
+        if self.unmarked_records().contains(&record.id) {
+            let marker = ownership.marker();
+            match self.set_record_remark(&record.id, &marker).await {
+                Ok(()) => {
+                    self.unmarked_records().remove(&record.id);
+                    record.remark = marker;
+                }
+                Err(e) => warn!(
+                    "❗️ [{}] Could not mark record {} as managed, retrying on its next write: {}",
+                    record_type, record.id, e
+                ),
+            }
+            return Ok(());
+        }
+        if !ownership.adopts_foreign() {
+            warn!(
+                "🚫 [{}] Record {} is not managed by this client (remark: '{}'). Leaving it alone.",
+                record_type, record.id, record.remark
+            );
+            return Err(DdnsError::ForeignRecord {
+                record_id: record.id.clone(),
+                remark: record.remark.clone(),
+            });
+        }
+        info!(
+            "🏷️ [{}] Adopting foreign record {} (remark: '{}')...",
+            record_type, record.id, record.remark
+        );
+        let marker = ownership.marker();
+        self.set_record_remark(&record.id, &marker).await?;
+        record.remark = marker;
+        Ok(())
+    }
+
+    /// Fetches all records and updates the internal state of both families.
     #[instrument(skip(self))]
     async fn refresh_state(&self) -> Result<()> {
         trace!("🌐 Refreshing local record state from DNSPod...");
-        let records = self.list_records().await?;
-        let mut state = self.state.lock().await;
-
-        // Reset current state
-        state.a = None;
-        state.aaaa = None;
+        let records = self.list_managed_records().await?;
+        for id in unmatched_pins(&self.pinned, &records, &self.record_line) {
+            warn!(
+                "📌 Pinned record ID {} matches no A or AAAA record of '{}' on line '{}'; it is ignored.",
+                id, self.display_name, self.record_line
+            );
+        }
+        let mut found = Vec::new();
+        for is_ipv4 in [true, false] {
+            let record_type = &RecordType::for_family(is_ipv4);
+            let mut family = self.state.family(is_ipv4).lock();
+            self.fill_family(record_type, &mut family, &records);
+            family.version += 1;
+            self.report_foreign(record_type, &family.records);
+            found.push(describe(&family.records));
+        }
+        info!(
+            "💾 State refreshed: A record found ({}), AAAA record found ({})",
+            found[0], found[1]
+        );
+        Ok(())
+    }
 
-        for record in records {
-            let record_state = RecordState {
-                id: record.id.to_string(),
-                ip: record.value,
-            };
-            if record.record_type.eq_ignore_ascii_case("A") {
-                state.a = Some(record_state);
-            } else if record.record_type.eq_ignore_ascii_case("AAAA") {
-                state.aaaa = Some(record_state);
+    /// (Private) Reports the records that belong to another owner, so that a foreign
+    /// record shows up when the records are loaded rather than on the first change.
+    fn report_foreign(&self, record_type: &RecordType, records: &[RecordState]) {
+        let Some(ownership) = &self.ownership else {
+            return;
+        };
+        let unmarked = self.unmarked_records().clone();
+        for record in records
+            .iter()
+            .filter(|r| !ownership.owns(&r.remark) && !unmarked.contains(&r.id))
+        {
+            if ownership.adopts_foreign() {
+                info!(
+                    "🏷️ [{}] Record {} is not managed by this client yet (remark: '{}'); it is adopted on the next change.",
+                    record_type, record.id, record.remark
+                );
+            } else {
+                warn!(
+                    "🚫 [{}] Record {} is not managed by this client (remark: '{}'); it will be left alone.",
+                    record_type, record.id, record.remark
+                );
             }
         }
+    }
+
+    /// (Private) Fetches all records and updates the state of one family only.
+    async fn refresh_family(
+        &self,
+        record_type: &RecordType,
+        family: &mut FamilyState,
+    ) -> Result<()> {
+        trace!(
+            "🌐 [{}] Refreshing local record state from DNSPod...",
+            record_type
+        );
+        let records = self.list_managed_records().await?;
+        self.fill_family(record_type, family, &records);
+        self.report_foreign(record_type, &family.records);
         info!(
-            "💾 State refreshed: A record found ({}), AAAA record found ({})",
-            state.a.is_some(),
-            state.aaaa.is_some()
+            "💾 [{}] State refreshed: record found ({})",
+            record_type,
+            describe(&family.records)
         );
         Ok(())
     }
 
-    /// (Private) Calls Record.List API.
-    async fn list_records(&self) -> Result<Vec<ListRecord>> {
+    /// (Private) Replaces the cached records of a family with the `listed` records of its
+    /// type on the managed line.
+    fn fill_family(&self, record_type: &RecordType, family: &mut FamilyState, listed: &[Record]) {
+        family.last_refresh = Some(Instant::now());
+        family.records = listed
+            .iter()
+            .filter(|record| &record.record_type == record_type)
+            .filter(|record| {
+                let on_line = record.line == self.record_line;
+                if !on_line {
+                    trace!(
+                        "Skipping record {} on line '{}' (managing line '{}')",
+                        record.id, record.line, self.record_line
+                    );
+                }
+                on_line
+            })
+            .map(|record| RecordState {
+                id: record.id.to_string(),
+                ip: record.value.clone(),
+                enabled: record.enabled,
+                remark: record.remark.clone(),
+            })
+            .collect();
+
+        order_records(&mut family.records, &self.pinned);
+        if family.records.len() > 1 {
+            let listed: Vec<String> = family
+                .records
+                .iter()
+                .map(|r| format!("{} -> {}", r.id, r.ip))
+                .collect();
+            let handling = match self.duplicates {
+                DuplicateStrategy::First => "only the first is managed",
+                DuplicateStrategy::All => "all of them are managed",
+                DuplicateStrategy::Collapse => "the others will be removed",
+            };
+            warn!(
+                "👯 [{}] Found {} records for '{}' on line '{}': {}. Primary is {}; {}.",
+                record_type,
+                family.records.len(),
+                self.display_name,
+                self.record_line,
+                listed.join(", "),
+                family.records[0].id,
+                handling
+            );
+        }
+    }
+
+    /// Lists every record of the zone matching `query`, following pagination.
+    #[instrument(skip(self))]
+    pub async fn list_records(&self, query: &RecordQuery) -> Result<Vec<Record>> {
+        let mut records = Vec::new();
+        let mut offset = Some(0);
+        while let Some(current) = offset {
+            let page = self.list_records_page(query, current).await?;
+            offset = page.next_offset();
+            records.extend(page.records);
+        }
+        debug!("Listed {} records of {}", records.len(), self.domain());
+        Ok(records)
+    }
+
+    /// Fetches one page of the records of the zone matching `query`, starting at `offset`.
+    pub async fn list_records_page(
+        &self,
+        query: &RecordQuery,
+        offset: usize,
+    ) -> Result<RecordPage> {
+        let offset_param = offset.to_string();
+        let length_param = query.page_size.to_string();
         let mut params: HashMap<&'static str, &str> = HashMap::new();
         params.insert("login_token", &self.token);
         params.insert("format", "json");
-        params.insert("domain", &self.domain);
-        params.insert("sub_domain", &self.sub_domain);
+        params.insert("domain", self.domain());
+        params.insert("offset", &offset_param);
+        params.insert("length", &length_param);
+        if let Some(sub_domain) = &query.sub_domain {
+            params.insert("sub_domain", sub_domain);
+        }
+        if let Some(record_type) = &query.record_type {
+            params.insert("record_type", record_type.as_str());
+        }
+        if let Some(record_line) = &query.record_line {
+            params.insert("record_line", record_line);
+        }
+        if let Some(keyword) = &query.keyword {
+            params.insert("keyword", keyword);
+        }
+
+        let res: ListResponse = self.post(API_RECORD_LIST, &params).await?;
+
+        match self.checked(API_RECORD_LIST, res.status) {
+            Ok(()) => {
+                let records = res.records.unwrap_or_default();
+                // Older responses may lack the total; then this page is all there is.
+                let total = res
+                    .info
+                    .map(|info| info.record_total)
+                    .unwrap_or(offset + records.len());
+                Ok(RecordPage {
+                    records,
+                    offset,
+                    total,
+                })
+            }
+            Err(DdnsError::Api {
+                code: ApiCode::NoRecords,
+                ..
+            }) => Ok(RecordPage {
+                records: vec![],
+                offset,
+                total: 0,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// (Private) Lists all records of the managed sub-domain, after resolving the records
+    /// that cannot coexist with its A and AAAA records.
+    async fn list_managed_records(&self) -> Result<Vec<Record>> {
+        let mut records = self.list_sub_domain_records().await?;
+        self.resolve_conflicts(&mut records).await?;
+        Ok(records)
+    }
+
+    /// (Private) Lists all records of the managed sub-domain as they are.
+    async fn list_sub_domain_records(&self) -> Result<Vec<Record>> {
+        self.list_records(&RecordQuery::new().with_sub_domain(self.sub_domain()))
+            .await
+    }
 
-        let url = format!("{}{}", API_BASE, API_RECORD_LIST);
-        let res: ListResponse = robust_post(&self.client, &url, &params).await?;
+    /// (Private) Looks for a CNAME or URL forwarding record on the managed line, or an NS
+    /// delegation of the sub-domain. Each one is deleted if replacing is enabled and it is
+    /// not a delegation; otherwise it is reported as `DdnsError::ConflictingRecord`.
+    /// With ownership enabled, records of other owners are only deleted if foreign records
+    /// are adopted; otherwise they are reported as `DdnsError::ForeignRecord`.
+    async fn resolve_conflicts(&self, records: &mut Vec<Record>) -> Result<()> {
+        // The NS records of the apex are the zone's own name servers.
+        let apex = self.sub_domain() == "@";
+        let conflicts: Vec<Record> = records
+            .iter()
+            .filter(|record| match record.record_type {
+                RecordType::Cname | RecordType::ExplicitUrl | RecordType::ImplicitUrl => {
+                    record.line == self.record_line
+                }
+                RecordType::Ns => !apex,
+                _ => false,
+            })
+            .cloned()
+            .collect();
 
-        if res.status.code != "1" {
-            if res.status.code == "10" {
-                return Ok(vec![]);
+        for conflict in conflicts {
+            let hint = match conflict.record_type {
+                RecordType::Ns => {
+                    "the name is delegated to other name servers; publish the address there or remove the delegation"
+                }
+                RecordType::Cname => {
+                    "a CNAME cannot coexist with other records; delete it or enable replacing conflicting records (--replace-conflicting)"
+                }
+                _ => {
+                    "URL forwarding serves the name itself; delete it or enable replacing conflicting records (--replace-conflicting)"
+                }
+            };
+            if !self.replace_conflicting || conflict.record_type == RecordType::Ns {
+                warn!(
+                    "⛔ {} record {} ('{}') at '{}' on line '{}' blocks the address records.",
+                    conflict.record_type,
+                    conflict.id,
+                    conflict.value,
+                    self.display_name,
+                    conflict.line
+                );
+                return Err(DdnsError::ConflictingRecord {
+                    name: self.display_name.clone(),
+                    record_id: conflict.id,
+                    record_type: conflict.record_type,
+                    value: conflict.value,
+                    hint,
+                });
             }
-            return Err(DdnsError::Api {
-                code: res.status.code,
-                message: res.status.message,
+            let foreign = self.ownership.as_ref().is_some_and(|ownership| {
+                !ownership.owns(&conflict.remark) && !ownership.adopts_foreign()
             });
+            if foreign {
+                warn!(
+                    "🚫 Conflicting {} record {} at '{}' is not managed by this client (remark: '{}'). Not replacing it.",
+                    conflict.record_type, conflict.id, self.display_name, conflict.remark
+                );
+                return Err(DdnsError::ForeignRecord {
+                    record_id: conflict.id,
+                    remark: conflict.remark,
+                });
+            }
+            warn!(
+                "🔀 Replacing conflicting {} record {} ('{}') at '{}'...",
+                conflict.record_type, conflict.id, conflict.value, self.display_name
+            );
+            self.delete_record(&conflict.id).await?;
+            records.retain(|record| record.id != conflict.id);
         }
-        Ok(res.records.unwrap_or_default())
+        Ok(())
     }
 
-    /// (Private) Calls Record.Modify API.
-    async fn modify_record(&self, record_type: &str, record_id: &str, ip: &str) -> Result<()> {
+    /// Lists the names of all domains (zones) of the account, following pagination.
+    #[instrument(skip(self))]
+    pub async fn list_domains(&self) -> Result<Vec<String>> {
+        let length_param = DOMAIN_PAGE_SIZE.to_string();
+        let mut domains = Vec::new();
+        loop {
+            let offset_param = domains.len().to_string();
+            let mut params: HashMap<&'static str, &str> = HashMap::new();
+            params.insert("login_token", &self.token);
+            params.insert("format", "json");
+            params.insert("type", "all");
+            params.insert("offset", &offset_param);
+            params.insert("length", &length_param);
+
+            let res: DomainListResponse = self.post(API_DOMAIN_LIST, &params).await?;
+            match self.checked(API_DOMAIN_LIST, res.status) {
+                Ok(()) => {}
+                Err(DdnsError::Api {
+                    code: ApiCode::NoDomains,
+                    ..
+                }) => break,
+                Err(e) => return Err(e),
+            }
+            let page = res.domains.unwrap_or_default();
+            let total = res.info.map(|info| info.domain_total).filter(|&t| t > 0);
+            if !add_domain_page(&mut domains, page, total) {
+                break;
+            }
+        }
+        debug!("Listed {} domains", domains.len());
+        Ok(domains)
+    }
+
+    /// Fetches a single record of the zone by its ID via Record.Info.
+    #[instrument(skip(self))]
+    pub async fn get_record(&self, record_id: &str) -> Result<Record> {
         let mut params: HashMap<&'static str, &str> = HashMap::new();
         params.insert("login_token", &self.token);
         params.insert("format", "json");
-        params.insert("domain", &self.domain);
+        params.insert("domain", self.domain());
         params.insert("record_id", record_id);
-        params.insert("sub_domain", &self.sub_domain);
-        params.insert("record_type", record_type);
-        params.insert("record_line", "默认");
-        params.insert("value", ip);
-
-        let url = format!("{}{}", API_BASE, API_RECORD_MODIFY);
-        let res: ModifyResponse = robust_post(&self.client, &url, &params).await?;
-
-        if res.status.code != "1" {
-            return Err(DdnsError::Api {
-                code: res.status.code,
-                message: res.status.message,
-            });
+
+        let res: InfoResponse = self.post(API_RECORD_INFO, &params).await?;
+
+        self.checked(API_RECORD_INFO, res.status)?;
+        res.record.ok_or_else(|| DdnsError::Api {
+            code: ApiCode::UnknownError,
+            raw_code: "1".to_string(),
+            message: "Record.Info succeeded without returning the record".to_string(),
+        })
+    }
+
+    /// (Private) Points the managed record `record_id` at `ip`.
+    ///
+    /// DNSPod penalizes repeated identical modifications, so the record's live value is
+    /// read via Record.Info first (the cached one may be outdated), and a modify that
+    /// would not change it is never sent.
+    async fn modify_value(
+        &self,
+        record_type: &RecordType,
+        record_id: &str,
+        ip: &str,
+    ) -> Result<()> {
+        let live = self.get_record(record_id).await?;
+        if same_value(&live.value, ip) {
+            info!(
+                "👌 [{}] Record {} already has value '{}'; not sending a modify.",
+                record_type, record_id, ip
+            );
+            return Ok(());
         }
-        Ok(())
+        let spec = RecordSpec::new(self.sub_domain(), record_type.clone(), ip)
+            .with_line(self.record_line.as_str());
+        self.modify_record(record_id, &spec).await
+    }
+
+    /// Replaces the content of record `record_id` of the zone via Record.Modify.
+    ///
+    /// DNSPod penalizes repeated identical modifications, so after a transient failure the
+    /// modify is only sent again once Record.Info shows that it did not take effect.
+    #[instrument(skip(self), fields(attempts = tracing::field::Empty))]
+    pub async fn modify_record(&self, record_id: &str, spec: &RecordSpec) -> Result<()> {
+        let ttl = spec.ttl.map(|ttl| ttl.to_string());
+        let mx = spec.mx.map(|mx| mx.to_string());
+        let mut params = self.spec_params(spec, ttl.as_deref(), mx.as_deref());
+        params.insert("record_id", record_id);
+        if self.skip_write(API_RECORD_MODIFY, &params) {
+            return Ok(());
+        }
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            Span::current().record("attempts", attempt);
+            let err = match self
+                .post_once::<ModifyResponse>(API_RECORD_MODIFY, &params)
+                .await
+            {
+                Ok(res) => return self.checked(API_RECORD_MODIFY, res.status),
+                Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => e,
+                Err(e) => return Err(e),
+            };
+
+            warn!(
+                "❗️ Record.Modify failed transiently (attempt {}/{}): {}. Checking whether it was applied...",
+                attempt, MAX_ATTEMPTS, err
+            );
+            let live = self.get_record(record_id).await?;
+            if same_value(&live.value, &spec.value) {
+                info!(
+                    "✅ The record was modified despite the error (ID: {}).",
+                    record_id
+                );
+                return Ok(());
+            }
+            time::sleep(backoff_delay(attempt)).await;
+        }
+    }
+
+    /// Creates a record in the zone via Record.Create and returns it as submitted.
+    ///
+    /// A create is not idempotent: after a transient failure the record may or may not
+    /// exist, so it is only retried once a listing confirms it was not created.
+    #[instrument(skip(self), fields(attempts = tracing::field::Empty))]
+    pub async fn create_record(&self, spec: &RecordSpec) -> Result<Record> {
+        let ttl = spec.ttl.map(|ttl| ttl.to_string());
+        let mx = spec.mx.map(|mx| mx.to_string());
+        let params = self.spec_params(spec, ttl.as_deref(), mx.as_deref());
+        let created = |id: String| Record {
+            id,
+            name: spec.sub_domain.clone(),
+            record_type: spec.record_type.clone(),
+            value: spec.value.clone(),
+            line: spec.line.clone(),
+            ttl: spec.ttl.unwrap_or_default(),
+            mx: spec.mx.unwrap_or_default(),
+            enabled: true,
+            remark: String::new(),
+            updated_on: String::new(),
+        };
+        if self.skip_write(API_RECORD_CREATE, &params) {
+            return Ok(created("dry-run".to_string()));
+        }
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            Span::current().record("attempts", attempt);
+            let err = match self
+                .post_once::<CreateResponse>(API_RECORD_CREATE, &params)
+                .await
+            {
+                Ok(res) => {
+                    self.checked(API_RECORD_CREATE, res.status)?;
+                    return res
+                        .record
+                        .map(|record| created(record.id.to_string()))
+                        .ok_or_else(|| DdnsError::Api {
+                            code: ApiCode::UnknownError,
+                            raw_code: "1".to_string(),
+                            message: "Record.Create succeeded without returning the record"
+                                .to_string(),
+                        });
+                }
+                Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => e,
+                Err(e) => return Err(e),
+            };
+
+            warn!(
+                "❗️ Record.Create failed transiently (attempt {}/{}): {}. Checking whether it was created...",
+                attempt, MAX_ATTEMPTS, err
+            );
+            let query = RecordQuery::new()
+                .with_sub_domain(spec.sub_domain.as_str())
+                .with_record_type(spec.record_type.clone());
+            let existing = self
+                .list_records(&query)
+                .await?
+                .into_iter()
+                .find(|r| r.line == spec.line && r.value == spec.value);
+            if let Some(record) = existing {
+                info!(
+                    "✅ The record was created despite the error (ID: {}).",
+                    record.id
+                );
+                return Ok(record);
+            }
+            time::sleep(backoff_delay(attempt)).await;
+        }
+    }
+
+    /// Enables or disables record `record_id` of the zone via Record.Status.
+    #[instrument(skip(self))]
+    pub async fn set_record_status(&self, record_id: &str, enabled: bool) -> Result<()> {
+        let mut params: HashMap<&'static str, &str> = HashMap::new();
+        params.insert("login_token", &self.token);
+        params.insert("format", "json");
+        params.insert("domain", self.domain());
+        params.insert("record_id", record_id);
+        params.insert("status", if enabled { "enable" } else { "disable" });
+        if self.skip_write(API_RECORD_STATUS, &params) {
+            return Ok(());
+        }
+
+        let res: ModifyResponse = self.post(API_RECORD_STATUS, &params).await?;
+
+        self.checked(API_RECORD_STATUS, res.status)
+    }
+
+    /// Sets the remark of record `record_id` of the zone via Record.Remark.
+    #[instrument(skip(self))]
+    pub async fn set_record_remark(&self, record_id: &str, remark: &str) -> Result<()> {
+        let mut params: HashMap<&'static str, &str> = HashMap::new();
+        params.insert("login_token", &self.token);
+        params.insert("format", "json");
+        params.insert("domain", self.domain());
+        params.insert("record_id", record_id);
+        params.insert("remark", remark);
+        if self.skip_write(API_RECORD_REMARK, &params) {
+            return Ok(());
+        }
+
+        let res: ModifyResponse = self.post(API_RECORD_REMARK, &params).await?;
+
+        self.checked(API_RECORD_REMARK, res.status)
     }
 
-    /// (Private) Calls Record.Create API.
-    async fn create_record(&self, record_type: &str, ip: &str) -> Result<CreatedRecord> {
+    /// Deletes record `record_id` of the zone via Record.Remove.
+    ///
+    /// A record that is already gone counts as deleted.
+    #[instrument(skip(self))]
+    pub async fn delete_record(&self, record_id: &str) -> Result<()> {
         let mut params: HashMap<&'static str, &str> = HashMap::new();
         params.insert("login_token", &self.token);
         params.insert("format", "json");
-        params.insert("domain", &self.domain);
-        params.insert("sub_domain", &self.sub_domain);
-        params.insert("record_type", record_type);
-        params.insert("record_line", "默认");
-        params.insert("value", ip);
-
-        let url = format!("{}{}", API_BASE, API_RECORD_CREATE);
-        let res: CreateResponse = robust_post(&self.client, &url, &params).await?;
-        if res.status.code != "1" {
-            return Err(DdnsError::Api {
-                code: res.status.code,
-                message: res.status.message,
+        params.insert("domain", self.domain());
+        params.insert("record_id", record_id);
+        if self.skip_write(API_RECORD_REMOVE, &params) {
+            return Ok(());
+        }
+
+        let res: ModifyResponse = self.post(API_RECORD_REMOVE, &params).await?;
+
+        // A retried remove may find the record already gone, which is what we wanted.
+        match self.checked(API_RECORD_REMOVE, res.status) {
+            Err(DdnsError::Api {
+                code: ApiCode::RecordIdInvalid,
+                ..
+            }) => {
+                debug!("Record {} is already gone.", record_id);
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    /// (Private) In dry-run mode, logs the write request instead of sending it and returns true.
+    fn skip_write(&self, path: &str, params: &HashMap<&'static str, &str>) -> bool {
+        if !self.dry_run {
+            return false;
+        }
+        info!(
+            "🧪 [dry-run] Would POST {}{} with {}",
+            API_BASE,
+            path,
+            redacted(params)
+        );
+        true
+    }
+
+    /// (Private) Builds the Record.Create/Record.Modify parameters of a record spec.
+    fn spec_params<'a>(
+        &'a self,
+        spec: &'a RecordSpec,
+        ttl: Option<&'a str>,
+        mx: Option<&'a str>,
+    ) -> HashMap<&'static str, &'a str> {
+        let mut params: HashMap<&'static str, &str> = HashMap::new();
+        params.insert("login_token", &self.token);
+        params.insert("format", "json");
+        params.insert("domain", self.domain());
+        params.insert("sub_domain", &spec.sub_domain);
+        params.insert("record_type", spec.record_type.as_str());
+        params.insert("record_line", &spec.line);
+        params.insert("value", &spec.value);
+        if let Some(ttl) = ttl {
+            params.insert("ttl", ttl);
+        }
+        if let Some(mx) = mx {
+            params.insert("mx", mx);
+        }
+        params
+    }
+
+    /// (Private) Sends an idempotent API request, retrying connection errors, timeouts
+    /// and 5xx responses with exponential backoff and full jitter.
+    #[instrument(skip(self, params), fields(attempts = tracing::field::Empty))]
+    async fn post<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        params: &HashMap<&'static str, &str>,
+    ) -> Result<T> {
+        if self.zone.get().is_none() && path != API_DOMAIN_LIST {
+            return Err(DdnsError::Config(format!(
+                "The zone of '{}' is not known yet; call initialize first",
+                self.display_name
+            )));
+        }
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            Span::current().record("attempts", attempt);
+            match self.post_once(path, params).await {
+                Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                    let delay = backoff_delay(attempt);
+                    debug!(
+                        "Transient error calling {} (attempt {}/{}): {}. Retrying in {:?}...",
+                        path, attempt, MAX_ATTEMPTS, e, delay
+                    );
+                    time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// (Private) Sends a single API request through the rate limiter, failing fast while
+    /// DNSPod is throttling this account.
+    async fn post_once<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        params: &HashMap<&'static str, &str>,
+    ) -> Result<T> {
+        if let Some(remaining) = self.limiter.remaining_ban() {
+            return Err(DdnsError::Throttled {
+                remaining_secs: remaining.as_secs(),
             });
         }
+        self.limiter.acquire().await;
+        let url = format!("{}{}", API_BASE, path);
+        robust_post(&self.client, &url, params).await
+    }
 
-        Ok(res.record)
+    /// (Private) Converts a response status of `endpoint` into a result, starting a
+    /// cool-down if DNSPod reported throttling.
+    fn checked(&self, endpoint: &str, status: Status) -> Result<()> {
+        let result = status.into_result(endpoint);
+        match &result {
+            Err(DdnsError::Api {
+                code: ApiCode::RateLimited,
+                ..
+            }) => self.limiter.ban_for(RATE_LIMITED_COOLDOWN),
+            Err(DdnsError::Api {
+                code: ApiCode::FrequentModification,
+                ..
+            }) => self.limiter.ban_for(FREQUENT_MODIFICATION_COOLDOWN),
+            _ => {}
+        }
+        result
+    }
+}
+
+/// Whether a record's live value equals `value`, comparing addresses by their parsed form.
+fn same_value(live: &str, value: &str) -> bool {
+    match (live.parse::<IpAddr>(), value.parse::<IpAddr>()) {
+        (Ok(live), Ok(value)) => live == value,
+        _ => live == value,
+    }
+}
+
+/// Puts the primary record first: pinned IDs, then the lowest ID, so the choice is deterministic.
+fn order_records(records: &mut [RecordState], pinned: &[String]) {
+    records.sort_by_key(|r| {
+        (
+            !pinned.contains(&r.id),
+            r.id.parse::<u64>().unwrap_or(u64::MAX),
+            r.id.clone(),
+        )
+    });
+}
+
+/// The pinned IDs that match no A or AAAA record of the `listed` records on `line`, so
+/// they cannot pick the primary record of either family.
+fn unmatched_pins<'a>(pinned: &'a [String], listed: &[Record], line: &str) -> Vec<&'a str> {
+    pinned
+        .iter()
+        .filter(|id| {
+            !listed.iter().any(|record| {
+                &record.id == *id
+                    && record.line == line
+                    && matches!(record.record_type, RecordType::A | RecordType::Aaaa)
+            })
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+/// Formats request parameters for logging, sorted and with the login token redacted.
+fn redacted(params: &HashMap<&'static str, &str>) -> String {
+    let mut pairs: Vec<String> = params
+        .iter()
+        .filter(|(key, _)| **key != "format")
+        .map(|(key, value)| match *key {
+            "login_token" => format!("{}=<redacted>", key),
+            _ => format!("{}={}", key, value),
+        })
+        .collect();
+    pairs.sort();
+    pairs.join(" ")
+}
+
+/// Summarizes the cached records of one type for the refresh log line.
+fn describe(records: &[RecordState]) -> String {
+    match records {
+        [] => "false".to_string(),
+        [r] if r.enabled => "true".to_string(),
+        [_] => "true, disabled".to_string(),
+        _ => format!("true, {} records", records.len()),
     }
 }
 
@@ -296,6 +2072,13 @@ async fn robust_post<T: for<'de> Deserialize<'de>>(
 ) -> Result<T> {
     let response = client.post(url).form(params).send().await?;
 
+    // Gateway errors carry an HTML page, not an API status; surface them as HTTP errors.
+    let response = if response.status().is_server_error() {
+        response.error_for_status()?
+    } else {
+        response
+    };
+
     let body_text = response.text().await?;
 
     match serde_json::from_str(&body_text) {
@@ -306,3 +2089,168 @@ async fn robust_post<T: for<'de> Deserialize<'de>>(
         }),
     }
 }
+
+/// Returns true for errors worth retrying right away: connection failures, timeouts and 5xx.
+fn is_transient(e: &DdnsError) -> bool {
+    match e {
+        DdnsError::Network(e) => {
+            e.is_connect()
+                || e.is_timeout()
+                || e.is_request()
+                || e.is_body()
+                || e.status().is_some_and(|s| s.is_server_error())
+        }
+        _ => false,
+    }
+}
+
+/// Exponential backoff with full jitter: a random delay up to `base * 2^(attempt - 1)`.
+fn backoff_delay(attempt: u32) -> Duration {
+    let cap = RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(RETRY_MAX_DELAY);
+    Duration::from_millis(rand::random_range(0..=cap.as_millis() as u64))
+}
+
+/// (Private) Adds a page of `Domain.List` to `domains`. Returns true if another page
+/// follows: fewer than `total` domains were listed so far, or, if DNSPod did not report
+/// the total, the page was full. An empty page always ends the listing.
+fn add_domain_page(
+    domains: &mut Vec<String>,
+    page: Vec<DomainEntry>,
+    total: Option<usize>,
+) -> bool {
+    let full = page.len() >= DOMAIN_PAGE_SIZE;
+    if page.is_empty() {
+        return false;
+    }
+    domains.extend(page.into_iter().map(|domain| domain.name));
+    match total {
+        Some(total) => domains.len() < total,
+        None => full,
+    }
+}
+
+/// (Private) The instant of a Unix timestamp in the past.
+fn instant_of(unix_secs: u64) -> Instant {
+    Instant::now()
+        .checked_sub(age_of(unix_secs))
+        .unwrap_or_else(Instant::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(names: &[&str]) -> Vec<DomainEntry> {
+        names
+            .iter()
+            .map(|name| DomainEntry {
+                name: name.to_string(),
+            })
+            .collect()
+    }
+
+    /// Feeds `pages` to `add_domain_page` like `list_domains` does and returns the
+    /// domains and the number of pages requested.
+    fn list(pages: Vec<Vec<DomainEntry>>, total: Option<usize>) -> (Vec<String>, usize) {
+        let mut domains = Vec::new();
+        let mut requested = 0;
+        for page in pages {
+            requested += 1;
+            if !add_domain_page(&mut domains, page, total) {
+                break;
+            }
+        }
+        (domains, requested)
+    }
+
+    #[test]
+    fn domain_listing_stops_at_the_total() {
+        let pages = vec![
+            page(&["a.com", "b.com"]),
+            page(&["c.com"]),
+            page(&["d.com"]),
+        ];
+        let (domains, requested) = list(pages, Some(3));
+        assert_eq!(domains, ["a.com", "b.com", "c.com"]);
+        assert_eq!(requested, 2);
+
+        let (domains, requested) = list(vec![page(&["a.com"]), page(&["b.com"])], Some(1));
+        assert_eq!(domains, ["a.com"]);
+        assert_eq!(requested, 1);
+    }
+
+    #[test]
+    fn domain_listing_stops_on_an_empty_page() {
+        // A total that was never reached must not loop forever.
+        let pages = vec![page(&["a.com"]), page(&[]), page(&["b.com"])];
+        let (domains, requested) = list(pages, Some(10));
+        assert_eq!(domains, ["a.com"]);
+        assert_eq!(requested, 2);
+    }
+
+    #[test]
+    fn domain_listing_without_total_stops_on_a_short_page() {
+        let full: Vec<String> = (0..DOMAIN_PAGE_SIZE)
+            .map(|i| format!("{}.com", i))
+            .collect();
+        let full: Vec<&str> = full.iter().map(String::as_str).collect();
+        let pages = vec![page(&full), page(&["last.com"]), page(&["never.com"])];
+        let (domains, requested) = list(pages, None);
+        assert_eq!(domains.len(), DOMAIN_PAGE_SIZE + 1);
+        assert_eq!(requested, 2);
+    }
+
+    fn record(id: &str, record_type: &str, line: &str) -> Record {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "type": record_type,
+            "value": "198.51.100.7",
+            "line": line,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn pins_must_match_an_address_record_on_the_line() {
+        let listed = vec![
+            record("11", "A", "默认"),
+            record("12", "AAAA", "默认"),
+            record("13", "A", "电信"),
+            record("14", "CNAME", "默认"),
+        ];
+        let pinned: Vec<String> = ["11", "12", "13", "14", "15"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        assert_eq!(
+            unmatched_pins(&pinned, &listed, "默认"),
+            vec!["13", "14", "15"]
+        );
+        assert!(unmatched_pins(&[], &listed, "默认").is_empty());
+    }
+
+    #[test]
+    fn values_compare_as_addresses() {
+        assert!(same_value("2001:db8::7", "2001:0db8:0:0::7"));
+        assert!(same_value("198.51.100.7", "198.51.100.7"));
+        assert!(!same_value("198.51.100.7", "198.51.100.8"));
+        assert!(same_value("target.example.com.", "target.example.com."));
+    }
+
+    #[test]
+    fn logged_parameters_hide_the_token() {
+        let mut params: HashMap<&'static str, &str> = HashMap::new();
+        params.insert("login_token", "12345,secret");
+        params.insert("format", "json");
+        params.insert("value", "198.51.100.7");
+        params.insert("domain", "example.com");
+        let logged = redacted(&params);
+        assert_eq!(
+            logged,
+            "domain=example.com login_token=<redacted> value=198.51.100.7"
+        );
+        assert!(!logged.contains("secret"));
+    }
+}
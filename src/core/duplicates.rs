@@ -0,0 +1,45 @@
+use crate::error::DdnsError;
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do when a name has several records of the same type on the managed line.
+///
+/// Records are ordered with pinned record IDs first, then by ascending ID; the first
+/// one is the primary record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateStrategy {
+    /// Manage only the primary record and leave the others untouched.
+    #[default]
+    First,
+    /// Keep every record pointing at the detected address.
+    All,
+    /// Delete all records except the primary one via `Record.Remove`.
+    Collapse,
+}
+
+impl FromStr for DuplicateStrategy {
+    type Err = DdnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "first" => Ok(DuplicateStrategy::First),
+            "all" => Ok(DuplicateStrategy::All),
+            "collapse" => Ok(DuplicateStrategy::Collapse),
+            other => Err(DdnsError::Config(format!(
+                "Unknown duplicate strategy '{}', expected first, all or collapse",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for DuplicateStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DuplicateStrategy::First => "first",
+            DuplicateStrategy::All => "all",
+            DuplicateStrategy::Collapse => "collapse",
+        };
+        f.write_str(name)
+    }
+}
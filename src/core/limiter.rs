@@ -0,0 +1,87 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time;
+use tracing::{trace, warn};
+
+/// A token-bucket limiter for DNSPod API calls, plus a cool-down after throttling responses.
+///
+/// One limiter can be shared by several `DnspodClient`s that use the same account.
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    inner: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    banned_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Allows `per_minute` calls per minute on average, with bursts of up to `burst` calls.
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            capacity,
+            refill_per_sec: per_minute.max(1) as f64 / 60.0,
+            inner: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+                banned_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a call may be made.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.inner.lock().expect("rate limiter lock poisoned");
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec)
+            };
+            trace!("API rate limit reached; waiting {:?}", wait);
+            time::sleep(wait).await;
+        }
+    }
+
+    /// Stops all calls for `duration` after DNSPod reported throttling or a ban.
+    pub fn ban_for(&self, duration: Duration) {
+        let mut bucket = self.inner.lock().expect("rate limiter lock poisoned");
+        let until = Instant::now() + duration;
+        if bucket.banned_until.is_none_or(|current| current < until) {
+            warn!(
+                "🐢 DNSPod is throttling this account. Pausing API calls for {:?}.",
+                duration
+            );
+            bucket.banned_until = Some(until);
+        }
+    }
+
+    /// How long API calls remain paused, if they are.
+    pub fn remaining_ban(&self) -> Option<Duration> {
+        let bucket = self.inner.lock().expect("rate limiter lock poisoned");
+        bucket
+            .banned_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+impl Default for RateLimiter {
+    /// 30 calls per minute with bursts of 5, well below DNSPod's documented limits.
+    fn default() -> Self {
+        Self::new(30, 5)
+    }
+}
@@ -1,5 +1,28 @@
 mod constants;
 mod dnspod;
+mod duplicates;
+mod limiter;
+mod name;
+mod outage;
+mod outcome;
+mod owner;
+mod persist;
+mod query;
+mod record;
+mod shutdown;
+mod stability;
+mod status;
 
-pub use constants::API_BASE;
+pub use constants::{API_BASE, DEFAULT_RECORD_LINE};
 pub use dnspod::DnspodClient;
+pub use duplicates::DuplicateStrategy;
+pub use limiter::RateLimiter;
+pub use outage::OutageAction;
+pub use outcome::{SkipReason, UpdateOutcome};
+pub use owner::Ownership;
+pub use persist::StateStore;
+pub use query::{RecordPage, RecordQuery};
+pub use record::{Record, RecordSpec, RecordType};
+pub use shutdown::ShutdownAction;
+pub use stability::StabilityPolicy;
+pub use status::ApiCode;
@@ -0,0 +1,227 @@
+use crate::error::{DdnsError, Result};
+
+/// The longest domain name DNS allows, without the trailing dot.
+const MAX_NAME_LEN: usize = 253;
+/// The longest label DNS allows.
+const MAX_LABEL_LEN: usize = 63;
+
+/// (Crate) Converts a domain name to the ASCII form sent to DNSPod and checks every label.
+///
+/// Internationalized names such as "例子.中国" are mapped with UTS #46 and encoded as
+/// punycode ("xn--fsqu00a.xn--fiqs8s"); the trailing dot is dropped. The leftmost label
+/// may be `*` for a wildcard, or `@` for the zone apex, which is dropped as well:
+/// `@.example.com` becomes `example.com`.
+pub(crate) fn normalize_name(name: &str) -> Result<String> {
+    let invalid = |reason: String| DdnsError::InvalidName {
+        name: name.to_string(),
+        reason,
+    };
+    let trimmed = name.trim().trim_end_matches('.');
+    if trimmed.is_empty() {
+        return Err(invalid("it is empty".to_string()));
+    }
+    // The special leftmost labels are not domain labels; keep them out of the IDNA mapping.
+    let (special, rest) = match trimmed.split_once('.') {
+        Some((first @ ("*" | "@"), rest)) => (Some(first), rest),
+        _ => (None, trimmed),
+    };
+    let ascii = idna::domain_to_ascii(rest)
+        .map_err(|_| invalid("it is not a valid internationalized domain name".to_string()))?;
+    let normalized = match special {
+        Some(first) => format!("{}.{}", first, ascii),
+        None => ascii,
+    };
+    if normalized.len() > MAX_NAME_LEN {
+        return Err(invalid(format!(
+            "it is longer than {} characters",
+            MAX_NAME_LEN
+        )));
+    }
+
+    for (index, label) in normalized.split('.').enumerate() {
+        if index == 0 && (label == "*" || label == "@") {
+            continue;
+        }
+        if label.is_empty() {
+            return Err(invalid("it contains an empty label".to_string()));
+        }
+        if label.len() > MAX_LABEL_LEN {
+            return Err(invalid(format!(
+                "label '{}' is longer than {} characters",
+                label, MAX_LABEL_LEN
+            )));
+        }
+        if let Some(c) = label
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+        {
+            return Err(invalid(format!("label '{}' contains '{}'", label, c)));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(invalid(format!(
+                "label '{}' starts or ends with a hyphen",
+                label
+            )));
+        }
+    }
+
+    match normalized.strip_prefix("@.") {
+        Some(apex) => Ok(apex.to_string()),
+        None => Ok(normalized),
+    }
+}
+
+/// (Crate) Converts a normalized name back to Unicode for logs and notifications.
+/// Names that are not valid punycode are returned as-is.
+pub(crate) fn display_name(name: &str) -> String {
+    let (unicode, result) = idna::domain_to_unicode(name);
+    if result.is_ok() {
+        unicode
+    } else {
+        name.to_string()
+    }
+}
+
+/// (Crate) Splits a normalized name into the longest of `zones` that contains it and the
+/// sub-domain within that zone, `@` for the apex. Returns `None` if no zone matches.
+pub(crate) fn split_name(name: &str, zones: &[String]) -> Option<(String, String)> {
+    zones
+        .iter()
+        .map(|zone| zone.trim_end_matches('.').to_ascii_lowercase())
+        .filter(|zone| {
+            name == zone
+                || name
+                    .strip_suffix(zone.as_str())
+                    .is_some_and(|rest| rest.ends_with('.'))
+        })
+        .max_by_key(|zone| zone.len())
+        .map(|zone| {
+            let sub_domain = if name == zone {
+                "@".to_string()
+            } else {
+                name[..name.len() - zone.len() - 1].to_string()
+            };
+            (zone, sub_domain)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zones(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn split(name: &str, names: &[&str]) -> Option<(String, String)> {
+        split_name(name, &zones(names))
+    }
+
+    fn pair(zone: &str, sub_domain: &str) -> Option<(String, String)> {
+        Some((zone.to_string(), sub_domain.to_string()))
+    }
+
+    fn assert_invalid(name: &str) {
+        assert!(
+            matches!(normalize_name(name), Err(DdnsError::InvalidName { .. })),
+            "'{}' should be rejected, got {:?}",
+            name,
+            normalize_name(name)
+        );
+    }
+
+    #[test]
+    fn split_prefers_the_longest_zone() {
+        let names = ["com.cn", "example.com.cn", "other.com.cn"];
+        assert_eq!(
+            split("a.b.example.com.cn", &names),
+            pair("example.com.cn", "a.b")
+        );
+        assert_eq!(split("x.com.cn", &names), pair("com.cn", "x"));
+        // The order of the zones does not matter.
+        assert_eq!(
+            split("a.b.example.com.cn", &["example.com.cn", "com.cn"]),
+            pair("example.com.cn", "a.b")
+        );
+    }
+
+    #[test]
+    fn split_at_the_apex_and_wildcards() {
+        assert_eq!(
+            split("example.com", &["example.com"]),
+            pair("example.com", "@")
+        );
+        assert_eq!(
+            split("*.example.com", &["example.com"]),
+            pair("example.com", "*")
+        );
+        assert_eq!(
+            split("*.home.example.com", &["example.com"]),
+            pair("example.com", "*.home")
+        );
+    }
+
+    #[test]
+    fn split_matches_whole_labels_only() {
+        assert_eq!(split("notexample.com", &["example.com"]), None);
+        assert_eq!(split("a.notexample.com", &["example.com"]), None);
+        assert_eq!(split("example.com", &["a.example.com"]), None);
+    }
+
+    #[test]
+    fn split_tolerates_zone_spelling() {
+        assert_eq!(
+            split("home.example.com", &["Example.COM."]),
+            pair("example.com", "home")
+        );
+    }
+
+    #[test]
+    fn normalize_handles_apex_wildcard_and_trailing_dot() {
+        assert_eq!(normalize_name("@.example.com").unwrap(), "example.com");
+        assert_eq!(normalize_name("*.example.com").unwrap(), "*.example.com");
+        assert_eq!(
+            normalize_name(" Home.Example.COM. ").unwrap(),
+            "home.example.com"
+        );
+        assert_invalid("a.*.example.com");
+        assert_invalid("a.@.example.com");
+        assert_invalid(".");
+        assert_invalid("");
+    }
+
+    #[test]
+    fn normalize_checks_label_length() {
+        let longest = "a".repeat(63);
+        assert_eq!(
+            normalize_name(&format!("{}.example.com", longest)).unwrap(),
+            format!("{}.example.com", longest)
+        );
+        assert_invalid(&format!("{}.example.com", "a".repeat(64)));
+        let long_name = vec!["a".repeat(50); 6].join(".");
+        assert_invalid(&long_name);
+    }
+
+    #[test]
+    fn normalize_rejects_bad_labels() {
+        assert_invalid("-home.example.com");
+        assert_invalid("home-.example.com");
+        assert_invalid("home..example.com");
+        assert_invalid("ho me.example.com");
+        assert_eq!(
+            normalize_name("_acme-challenge.example.com").unwrap(),
+            "_acme-challenge.example.com"
+        );
+    }
+
+    #[test]
+    fn internationalized_names_round_trip() {
+        let ascii = normalize_name("家.例子.中国").unwrap();
+        assert_eq!(ascii, "xn--fct.xn--fsqu00a.xn--fiqs8s");
+        assert_eq!(display_name(&ascii), "家.例子.中国");
+        assert_eq!(
+            split(&ascii, &["xn--fsqu00a.xn--fiqs8s"]),
+            pair("xn--fsqu00a.xn--fiqs8s", "xn--fct")
+        );
+    }
+}
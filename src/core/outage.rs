@@ -0,0 +1,42 @@
+use crate::error::DdnsError;
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do with a record whose address family has disappeared from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutageAction {
+    /// Leave the record untouched.
+    #[default]
+    Keep,
+    /// Disable the record via `Record.Status` and re-enable it once an address is back.
+    Disable,
+    /// Delete the record; it is recreated once an address is back.
+    Delete,
+}
+
+impl FromStr for OutageAction {
+    type Err = DdnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(OutageAction::Keep),
+            "disable" => Ok(OutageAction::Disable),
+            "delete" => Ok(OutageAction::Delete),
+            other => Err(DdnsError::Config(format!(
+                "Unknown outage action '{}', expected keep, disable or delete",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for OutageAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutageAction::Keep => "keep",
+            OutageAction::Disable => "disable",
+            OutageAction::Delete => "delete",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+use std::time::Duration;
+
+/// The result of a call to `DnspodClient::update_if_needed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateOutcome {
+    /// The record already points to the detected address.
+    Unchanged,
+    /// An existing record was modified.
+    Updated,
+    /// A new record was created.
+    Created,
+    /// A record paused during an outage was enabled again.
+    Reenabled,
+    /// A record changed or deleted out of band (e.g. in the DNSPod console) was restored.
+    Corrected,
+    /// A change was detected but deliberately not published yet.
+    Skipped(SkipReason),
+}
+
+impl UpdateOutcome {
+    /// Returns true if the record now points to a new address.
+    pub fn is_changed(&self) -> bool {
+        matches!(
+            self,
+            UpdateOutcome::Updated
+                | UpdateOutcome::Created
+                | UpdateOutcome::Reenabled
+                | UpdateOutcome::Corrected
+        )
+    }
+}
+
+/// Why a detected change was held back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The new address has not been observed consistently for long enough.
+    Unstable {
+        observations: u32,
+        required: u32,
+        stable_for: Duration,
+    },
+    /// The record was modified too recently.
+    TooSoon { remaining: Duration },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Unstable {
+                observations,
+                required,
+                stable_for,
+            } => write!(
+                f,
+                "address not stable yet (seen {}/{} times, unchanged for {}s)",
+                observations,
+                required,
+                stable_for.as_secs()
+            ),
+            SkipReason::TooSoon { remaining } => write!(
+                f,
+                "record modified too recently (next change allowed in {}s)",
+                remaining.as_secs()
+            ),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+/// The remark key that marks a record as managed by this tool.
+const MANAGED_BY: &str = "managed-by=ddns";
+
+/// Marks records created by this client and guards records it does not own.
+///
+/// Records are tagged via `Record.Remark` with `managed-by=ddns host=<owner_id>`.
+/// Records without a matching tag are treated as foreign and never modified,
+/// unless adopting foreign records is explicitly allowed.
+#[derive(Debug, Clone)]
+pub struct Ownership {
+    owner_id: String,
+    adopt_foreign: bool,
+}
+
+impl Ownership {
+    /// Creates an ownership policy for the given host/instance identifier.
+    pub fn new(owner_id: impl Into<String>) -> Self {
+        Self {
+            owner_id: owner_id.into(),
+            adopt_foreign: false,
+        }
+    }
+
+    /// Allows taking over records that lack the marker; they are tagged on first write.
+    pub fn adopt_foreign(mut self, adopt: bool) -> Self {
+        self.adopt_foreign = adopt;
+        self
+    }
+
+    /// Whether foreign records may be taken over.
+    pub fn adopts_foreign(&self) -> bool {
+        self.adopt_foreign
+    }
+
+    /// The remark written to records this client manages.
+    pub fn marker(&self) -> String {
+        format!("{} host={}", MANAGED_BY, self.owner_id)
+    }
+
+    /// Returns true if `remark` marks the record as managed by this owner.
+    pub fn owns(&self, remark: &str) -> bool {
+        let host = format!("host={}", self.owner_id);
+        let tokens: Vec<&str> = remark.split_whitespace().collect();
+        tokens.contains(&MANAGED_BY) && tokens.contains(&host.as_str())
+    }
+}
@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, trace, warn};
+
+/// The version of the on-disk format; files of other versions are ignored.
+const FORMAT_VERSION: u32 = 2;
+
+/// Numbers the temporary files of this process, so concurrent writers never share one.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// An optional on-disk cache of record IDs and published addresses.
+///
+/// A cached entry is trusted for `ttl` after the records were last read from DNSPod,
+/// so short-lived runs (e.g. single-shot runs from cron) need no `Record.List` call.
+/// The timers of flap protection and outage handling are restored even from older entries.
+/// One file can hold the state of several clients; writes replace it atomically.
+///
+/// The methods block on file I/O; async callers run them on a blocking thread.
+#[derive(Debug)]
+pub struct StateStore {
+    path: PathBuf,
+    ttl: Duration,
+    /// Serializes the read-modify-write cycles of clients sharing the store.
+    lock: Mutex<()>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StateFile {
+    version: u32,
+    #[serde(default)]
+    targets: BTreeMap<String, TargetEntry>,
+}
+
+/// The persisted state of one client.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub(crate) struct TargetEntry {
+    /// The zone managing the name, so a trusted entry needs no `Domain.List` call either.
+    #[serde(default)]
+    pub zone: Option<ZoneEntry>,
+    pub a: FamilyEntry,
+    pub aaaa: FamilyEntry,
+}
+
+/// The zone and sub-domain a name was resolved to, in ASCII.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ZoneEntry {
+    pub domain: String,
+    pub sub_domain: String,
+}
+
+/// The persisted state of one address family; each family is saved on its own.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub(crate) struct FamilyEntry {
+    /// When the records were last read from DNSPod, in seconds since the Unix epoch.
+    pub refreshed_at: u64,
+    pub records: Vec<RecordEntry>,
+    /// When this client last modified or created a record, in seconds since the Unix epoch.
+    pub published_at: Option<u64>,
+    /// When detection of this family started failing, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub missing_since: Option<u64>,
+    /// The change held back by flap protection.
+    #[serde(default)]
+    pub pending: Option<PendingEntry>,
+    /// Set while the records deleted by a shutdown have not been created again.
+    #[serde(default)]
+    pub released: bool,
+}
+
+/// A detected change that was still held back by flap protection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PendingEntry {
+    pub ip: String,
+    pub count: u32,
+    /// When the change was first observed, in seconds since the Unix epoch.
+    pub since: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RecordEntry {
+    pub id: String,
+    pub ip: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub remark: String,
+}
+
+impl StateStore {
+    /// Creates a store backed by the file at `path`, trusting entries for `ttl`.
+    pub fn new(path: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            path: path.into(),
+            ttl,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The path of the state file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// (Crate) Returns the entry for `key` if it exists, and whether its records are still
+    /// trusted.
+    pub(crate) fn load(&self, key: &str) -> Option<(TargetEntry, bool)> {
+        let _guard = self.lock.lock().expect("state file lock poisoned");
+        let file = self.read()?;
+        let entry = file.targets.get(key)?.clone();
+
+        // The entry is only as fresh as its least recently read family.
+        let age = age_of(entry.a.refreshed_at.min(entry.aaaa.refreshed_at));
+        if age > self.ttl {
+            debug!(
+                "Cached records of '{}' are {:?} old (trusted for {:?}); ignoring them.",
+                key, age, self.ttl
+            );
+            return Some((entry, false));
+        }
+        trace!("Using cached state of '{}' ({:?} old).", key, age);
+        Some((entry, true))
+    }
+
+    /// (Crate) Stores the zone and the state of one address family of `key`, replacing the
+    /// file atomically.
+    pub(crate) fn save(
+        &self,
+        key: &str,
+        zone: ZoneEntry,
+        is_ipv4: bool,
+        entry: FamilyEntry,
+    ) -> io::Result<()> {
+        let _guard = self.lock.lock().expect("state file lock poisoned");
+        let mut file = self.read().unwrap_or_default();
+        file.version = FORMAT_VERSION;
+        let target = file.targets.entry(key.to_string()).or_default();
+        target.zone = Some(zone);
+        if is_ipv4 {
+            target.a = entry;
+        } else {
+            target.aaaa = entry;
+        }
+
+        let body = serde_json::to_vec_pretty(&file)?;
+        // Write to a sibling file first so readers never see a partial state file. Its name
+        // is unique, so another process writing the same file (e.g. a cron run overlapping
+        // the daemon) cannot clobber it before the rename.
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let written = fs::File::create(&tmp_path).and_then(|mut tmp| {
+            tmp.write_all(&body)?;
+            tmp.sync_all()?;
+            fs::rename(&tmp_path, &self.path)
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        written?;
+        trace!("Saved state of '{}' to {}", key, self.path.display());
+        Ok(())
+    }
+
+    /// (Private) Reads the state file; missing, unreadable or foreign files yield `None`.
+    fn read(&self) -> Option<StateFile> {
+        let body = match fs::read(&self.path) {
+            Ok(body) => body,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!("❗️ Cannot read state file {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        match serde_json::from_slice::<StateFile>(&body) {
+            Ok(file) if file.version == FORMAT_VERSION => Some(file),
+            Ok(file) => {
+                warn!(
+                    "❗️ State file {} has format version {}, expected {}; ignoring it.",
+                    self.path.display(),
+                    file.version,
+                    FORMAT_VERSION
+                );
+                None
+            }
+            Err(e) => {
+                warn!(
+                    "❗️ State file {} is corrupt ({}); ignoring it.",
+                    self.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Seconds since the Unix epoch of a point `ago` in the past.
+pub(crate) fn unix_secs_ago(ago: Duration) -> u64 {
+    SystemTime::now()
+        .checked_sub(ago)
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+/// How long ago a Unix timestamp was; zero for timestamps in the future.
+pub(crate) fn age_of(unix_secs: u64) -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH + Duration::from_secs(unix_secs))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "home.example.com@默认";
+
+    /// A store in a fresh directory of its own, removed again by `cleanup`.
+    fn store(name: &str, ttl: Duration) -> StateStore {
+        let dir =
+            std::env::temp_dir().join(format!("ddns-persist-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        StateStore::new(dir.join("state.json"), ttl)
+    }
+
+    fn cleanup(store: &StateStore) {
+        let _ = fs::remove_dir_all(store.path().parent().unwrap());
+    }
+
+    fn zone() -> ZoneEntry {
+        ZoneEntry {
+            domain: "example.com".to_string(),
+            sub_domain: "home".to_string(),
+        }
+    }
+
+    fn family(refreshed_ago: Duration) -> FamilyEntry {
+        FamilyEntry {
+            refreshed_at: unix_secs_ago(refreshed_ago),
+            records: vec![RecordEntry {
+                id: "11".to_string(),
+                ip: "198.51.100.7".to_string(),
+                enabled: true,
+                remark: String::new(),
+            }],
+            published_at: Some(unix_secs_ago(Duration::from_secs(60))),
+            pending: Some(PendingEntry {
+                ip: "198.51.100.8".to_string(),
+                count: 2,
+                since: unix_secs_ago(Duration::from_secs(30)),
+            }),
+            ..FamilyEntry::default()
+        }
+    }
+
+    #[test]
+    fn saved_entries_load_back() {
+        let store = store("round-trip", Duration::from_secs(3600));
+        let (a, aaaa) = (
+            family(Duration::ZERO),
+            FamilyEntry {
+                refreshed_at: unix_secs_ago(Duration::ZERO),
+                ..FamilyEntry::default()
+            },
+        );
+        store.save(KEY, zone(), true, a.clone()).unwrap();
+        store.save(KEY, zone(), false, aaaa.clone()).unwrap();
+
+        let (entry, trusted) = store.load(KEY).unwrap();
+        assert!(trusted);
+        assert_eq!(
+            entry,
+            TargetEntry {
+                zone: Some(zone()),
+                a,
+                aaaa,
+            }
+        );
+        assert!(store.load("other.example.com@默认").is_none());
+
+        // Only the state file is left behind.
+        let files: Vec<_> = fs::read_dir(store.path().parent().unwrap())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["state.json"]);
+        cleanup(&store);
+    }
+
+    #[test]
+    fn entries_older_than_the_ttl_are_not_trusted() {
+        let store = store("ttl", Duration::from_secs(60));
+        store
+            .save(KEY, zone(), true, family(Duration::ZERO))
+            .unwrap();
+        store
+            .save(KEY, zone(), false, family(Duration::from_secs(120)))
+            .unwrap();
+
+        let (entry, trusted) = store.load(KEY).unwrap();
+        assert!(!trusted);
+        assert_eq!(entry.a.pending, family(Duration::ZERO).pending);
+        cleanup(&store);
+    }
+
+    #[test]
+    fn files_of_another_version_are_ignored() {
+        let store = store("version", Duration::from_secs(3600));
+        store
+            .save(KEY, zone(), true, family(Duration::ZERO))
+            .unwrap();
+        let body = fs::read_to_string(store.path()).unwrap().replacen(
+            &format!("\"version\": {}", FORMAT_VERSION),
+            "\"version\": 1",
+            1,
+        );
+        fs::write(store.path(), body).unwrap();
+
+        assert!(store.load(KEY).is_none());
+        // The next save starts a file of the current version.
+        store
+            .save(KEY, zone(), true, family(Duration::ZERO))
+            .unwrap();
+        assert!(store.load(KEY).is_some());
+        cleanup(&store);
+    }
+
+    #[test]
+    fn corrupt_files_are_ignored() {
+        let store = store("corrupt", Duration::from_secs(3600));
+        fs::write(store.path(), "{\"version\": 2, \"targets\": ").unwrap();
+        assert!(store.load(KEY).is_none());
+
+        store
+            .save(KEY, zone(), true, family(Duration::ZERO))
+            .unwrap();
+        assert!(store.load(KEY).is_some());
+        cleanup(&store);
+    }
+}
@@ -0,0 +1,83 @@
+use super::record::{Record, RecordType};
+
+/// Filters and page size for listing the records of a zone via `Record.List`.
+///
+/// An empty query matches every record of the zone.
+#[derive(Debug, Clone)]
+pub struct RecordQuery {
+    pub(crate) sub_domain: Option<String>,
+    pub(crate) record_type: Option<RecordType>,
+    pub(crate) record_line: Option<String>,
+    pub(crate) keyword: Option<String>,
+    pub(crate) page_size: usize,
+}
+
+impl RecordQuery {
+    /// DNSPod returns at most 3000 records per request.
+    pub const MAX_PAGE_SIZE: usize = 3000;
+
+    /// Creates a query matching all records, fetched 500 at a time.
+    pub fn new() -> Self {
+        Self {
+            sub_domain: None,
+            record_type: None,
+            record_line: None,
+            keyword: None,
+            page_size: 500,
+        }
+    }
+
+    /// Only records of this sub-domain, e.g. "home" or "@".
+    pub fn with_sub_domain(mut self, sub_domain: impl Into<String>) -> Self {
+        self.sub_domain = Some(sub_domain.into());
+        self
+    }
+
+    /// Only records of this type, e.g. `RecordType::A` or `RecordType::Txt`.
+    pub fn with_record_type(mut self, record_type: RecordType) -> Self {
+        self.record_type = Some(record_type);
+        self
+    }
+
+    /// Only records on this line, e.g. "默认".
+    pub fn with_record_line(mut self, record_line: impl Into<String>) -> Self {
+        self.record_line = Some(record_line.into());
+        self
+    }
+
+    /// Only records whose name or value contains `keyword`.
+    pub fn with_keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.keyword = Some(keyword.into());
+        self
+    }
+
+    /// Records fetched per request, clamped to 1..=3000.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.clamp(1, Self::MAX_PAGE_SIZE);
+        self
+    }
+}
+
+impl Default for RecordQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// One page of a `Record.List` result.
+#[derive(Debug, Clone)]
+pub struct RecordPage {
+    pub records: Vec<Record>,
+    /// The offset of the first record of this page.
+    pub offset: usize,
+    /// The number of records matching the query across all pages.
+    pub total: usize,
+}
+
+impl RecordPage {
+    /// The offset of the next page, or `None` if this was the last one.
+    pub fn next_offset(&self) -> Option<usize> {
+        let next = self.offset + self.records.len();
+        (!self.records.is_empty() && next < self.total).then_some(next)
+    }
+}
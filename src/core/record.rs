@@ -0,0 +1,190 @@
+use super::constants::DEFAULT_RECORD_LINE;
+use crate::utils::{id_string, lenient_number};
+use serde::{Deserialize, Deserializer};
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// A DNSPod record type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+    Ns,
+    Srv,
+    Caa,
+    Https,
+    Svcb,
+    Spf,
+    /// DNSPod's "显性URL" forwarding record.
+    ExplicitUrl,
+    /// DNSPod's "隐性URL" forwarding record.
+    ImplicitUrl,
+    /// Any other type, kept verbatim.
+    Other(String),
+}
+
+impl RecordType {
+    /// The type name as DNSPod expects it in API calls.
+    pub fn as_str(&self) -> &str {
+        match self {
+            RecordType::A => "A",
+            RecordType::Aaaa => "AAAA",
+            RecordType::Cname => "CNAME",
+            RecordType::Mx => "MX",
+            RecordType::Txt => "TXT",
+            RecordType::Ns => "NS",
+            RecordType::Srv => "SRV",
+            RecordType::Caa => "CAA",
+            RecordType::Https => "HTTPS",
+            RecordType::Svcb => "SVCB",
+            RecordType::Spf => "SPF",
+            RecordType::ExplicitUrl => "显性URL",
+            RecordType::ImplicitUrl => "隐性URL",
+            RecordType::Other(name) => name,
+        }
+    }
+
+    /// The address record type of the given family.
+    pub fn for_family(is_ipv4: bool) -> Self {
+        if is_ipv4 {
+            RecordType::A
+        } else {
+            RecordType::Aaaa
+        }
+    }
+}
+
+impl FromStr for RecordType {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim().to_ascii_uppercase().as_str() {
+            "A" => RecordType::A,
+            "AAAA" => RecordType::Aaaa,
+            "CNAME" => RecordType::Cname,
+            "MX" => RecordType::Mx,
+            "TXT" => RecordType::Txt,
+            "NS" => RecordType::Ns,
+            "SRV" => RecordType::Srv,
+            "CAA" => RecordType::Caa,
+            "HTTPS" => RecordType::Https,
+            "SVCB" => RecordType::Svcb,
+            "SPF" => RecordType::Spf,
+            "显性URL" => RecordType::ExplicitUrl,
+            "隐性URL" => RecordType::ImplicitUrl,
+            other => RecordType::Other(other.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for RecordType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Ok(name
+            .parse()
+            .unwrap_or_else(|never: Infallible| match never {}))
+    }
+}
+
+/// A DNS record of a zone, as returned by `Record.List` and `Record.Info`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Record {
+    #[serde(deserialize_with = "id_string")]
+    pub id: String,
+    /// The sub-domain, e.g. "home" or "@".
+    #[serde(default, alias = "sub_domain")]
+    pub name: String,
+    #[serde(rename = "type", alias = "record_type")]
+    pub record_type: RecordType,
+    pub value: String,
+    #[serde(default, alias = "record_line")]
+    pub line: String,
+    #[serde(default, deserialize_with = "lenient_number")]
+    pub ttl: u32,
+    /// MX priority; 0 for other types.
+    #[serde(default, deserialize_with = "lenient_number")]
+    pub mx: u16,
+    /// False if the record is paused.
+    #[serde(default = "default_enabled", deserialize_with = "enabled_flag")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub remark: String,
+    /// Last modification time in DNSPod's format, e.g. "2024-01-01 12:00:00".
+    #[serde(default)]
+    pub updated_on: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// DNSPod reports the record status as "1" (enabled) or "0" (paused).
+fn enabled_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let flag: u8 = lenient_number(deserializer)?;
+    Ok(flag != 0)
+}
+
+/// The content of a record to create or to replace an existing record's content with.
+#[derive(Debug, Clone)]
+pub struct RecordSpec {
+    pub sub_domain: String,
+    pub record_type: RecordType,
+    pub value: String,
+    pub line: String,
+    /// `None` keeps DNSPod's default TTL for the plan.
+    pub ttl: Option<u32>,
+    /// MX priority; required for MX records.
+    pub mx: Option<u16>,
+}
+
+impl RecordSpec {
+    /// Creates a spec for the default line with the plan's default TTL.
+    pub fn new(
+        sub_domain: impl Into<String>,
+        record_type: RecordType,
+        value: impl Into<String>,
+    ) -> Self {
+        Self {
+            sub_domain: sub_domain.into(),
+            record_type,
+            value: value.into(),
+            line: DEFAULT_RECORD_LINE.to_string(),
+            ttl: None,
+            mx: None,
+        }
+    }
+
+    /// Sets the record line, e.g. "电信".
+    pub fn with_line(mut self, line: impl Into<String>) -> Self {
+        self.line = line.into();
+        self
+    }
+
+    /// Sets the TTL in seconds.
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the MX priority.
+    pub fn with_mx(mut self, mx: u16) -> Self {
+        self.mx = Some(mx);
+        self
+    }
+}
@@ -0,0 +1,42 @@
+use crate::error::DdnsError;
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do with the managed records when the daemon is asked to stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShutdownAction {
+    /// Leave the records untouched.
+    #[default]
+    Keep,
+    /// Delete the records; they are recreated on the next start.
+    Delete,
+    /// Point the records at a parking address; they are restored on the next start.
+    Park,
+}
+
+impl FromStr for ShutdownAction {
+    type Err = DdnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(ShutdownAction::Keep),
+            "delete" => Ok(ShutdownAction::Delete),
+            "park" => Ok(ShutdownAction::Park),
+            other => Err(DdnsError::Config(format!(
+                "Unknown shutdown action '{}', expected keep, delete or park",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ShutdownAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShutdownAction::Keep => "keep",
+            ShutdownAction::Delete => "delete",
+            ShutdownAction::Park => "park",
+        };
+        f.write_str(name)
+    }
+}
@@ -0,0 +1,252 @@
+use super::outcome::SkipReason;
+use std::time::{Duration, Instant};
+
+/// Rules a detected change must satisfy before it is published.
+///
+/// A new address is only published once it has been observed `min_observations`
+/// times in a row *or* has stayed unchanged for at least `min_stable`, whichever comes
+/// first; a threshold left at its default (one observation, zero seconds) does not count.
+/// Independently, a record is never modified more often than `min_update_interval`.
+#[derive(Debug, Clone)]
+pub struct StabilityPolicy {
+    pub min_observations: u32,
+    pub min_stable: Duration,
+    pub min_update_interval: Duration,
+}
+
+impl Default for StabilityPolicy {
+    /// Publishes every change immediately.
+    fn default() -> Self {
+        Self {
+            min_observations: 1,
+            min_stable: Duration::ZERO,
+            min_update_interval: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    ip: String,
+    count: u32,
+    since: Instant,
+}
+
+/// Per-record tracking of observed changes and the last modification time.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FlapGuard {
+    candidate: Option<Candidate>,
+    last_modified: Option<Instant>,
+}
+
+impl FlapGuard {
+    /// Records that the published address was observed again; any pending change is dropped.
+    pub(crate) fn settle(&mut self) {
+        self.candidate = None;
+    }
+
+    /// Records an observation of a changed address and decides whether it may be published.
+    pub(crate) fn observe(
+        &mut self,
+        ip: &str,
+        is_modify: bool,
+        policy: &StabilityPolicy,
+    ) -> std::result::Result<(), SkipReason> {
+        let now = Instant::now();
+        let candidate = match &mut self.candidate {
+            Some(c) if c.ip == ip => {
+                c.count = c.count.saturating_add(1);
+                c
+            }
+            slot => slot.insert(Candidate {
+                ip: ip.to_string(),
+                count: 1,
+                since: now,
+            }),
+        };
+
+        let stable_for = now.duration_since(candidate.since);
+        let by_count = policy.min_observations > 1;
+        let by_time = !policy.min_stable.is_zero();
+        let stable = !(by_count || by_time)
+            || (by_count && candidate.count >= policy.min_observations)
+            || (by_time && stable_for >= policy.min_stable);
+        if !stable {
+            return Err(SkipReason::Unstable {
+                observations: candidate.count,
+                required: policy.min_observations,
+                stable_for,
+            });
+        }
+
+        if is_modify {
+            if let Some(last) = self.last_modified {
+                let elapsed = now.duration_since(last);
+                if elapsed < policy.min_update_interval {
+                    return Err(SkipReason::TooSoon {
+                        remaining: policy.min_update_interval - elapsed,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that the record was just modified or created.
+    pub(crate) fn published(&mut self) {
+        self.candidate = None;
+        self.last_modified = Some(Instant::now());
+    }
+
+    /// Whether a modification or creation was recorded since `earlier` was taken.
+    pub(crate) fn published_since(&self, earlier: &FlapGuard) -> bool {
+        self.last_modified != earlier.last_modified
+    }
+
+    /// How long ago the record was last modified or created, if it was.
+    pub(crate) fn since_published(&self) -> Option<Duration> {
+        self.last_modified.map(|last| last.elapsed())
+    }
+
+    /// Restores the last modification time, e.g. from the state file of a previous run.
+    pub(crate) fn restore_published(&mut self, ago: Duration) {
+        self.last_modified = Some(Instant::now().checked_sub(ago).unwrap_or_else(Instant::now));
+    }
+
+    /// The change that is being held back, as its address, the number of observations and
+    /// how long ago it was first observed.
+    pub(crate) fn pending(&self) -> Option<(&str, u32, Duration)> {
+        self.candidate
+            .as_ref()
+            .map(|c| (c.ip.as_str(), c.count, c.since.elapsed()))
+    }
+
+    /// Restores a held back change, e.g. from the state file of a previous run.
+    pub(crate) fn restore_pending(&mut self, ip: &str, count: u32, ago: Duration) {
+        self.candidate = Some(Candidate {
+            ip: ip.to_string(),
+            count,
+            since: Instant::now().checked_sub(ago).unwrap_or_else(Instant::now),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: &str = "198.51.100.7";
+
+    fn policy(min_observations: u32, min_stable_secs: u64) -> StabilityPolicy {
+        StabilityPolicy {
+            min_observations,
+            min_stable: Duration::from_secs(min_stable_secs),
+            ..StabilityPolicy::default()
+        }
+    }
+
+    #[test]
+    fn default_policy_publishes_immediately() {
+        let mut guard = FlapGuard::default();
+        assert!(guard.observe(IP, true, &StabilityPolicy::default()).is_ok());
+    }
+
+    #[test]
+    fn count_threshold_alone() {
+        let policy = policy(3, 0);
+        let mut guard = FlapGuard::default();
+        for seen in 1..=2 {
+            match guard.observe(IP, true, &policy) {
+                Err(SkipReason::Unstable {
+                    observations,
+                    required,
+                    ..
+                }) => assert_eq!((observations, required), (seen, 3)),
+                other => panic!("expected Unstable, got {:?}", other),
+            }
+        }
+        assert!(guard.observe(IP, true, &policy).is_ok());
+    }
+
+    #[test]
+    fn time_threshold_alone() {
+        let policy = policy(1, 60);
+        let mut guard = FlapGuard::default();
+        assert!(guard.observe(IP, true, &policy).is_err());
+        assert!(guard.observe(IP, true, &policy).is_err());
+
+        guard.restore_pending(IP, 2, Duration::from_secs(61));
+        assert!(guard.observe(IP, true, &policy).is_ok());
+    }
+
+    #[test]
+    fn either_threshold_suffices() {
+        let policy = policy(3, 60);
+
+        // Enough observations before the time is up.
+        let mut guard = FlapGuard::default();
+        assert!(guard.observe(IP, true, &policy).is_err());
+        assert!(guard.observe(IP, true, &policy).is_err());
+        assert!(guard.observe(IP, true, &policy).is_ok());
+
+        // Enough time before enough observations.
+        let mut guard = FlapGuard::default();
+        guard.restore_pending(IP, 1, Duration::from_secs(61));
+        assert!(guard.observe(IP, true, &policy).is_ok());
+    }
+
+    #[test]
+    fn another_address_restarts_the_count() {
+        let policy = policy(2, 0);
+        let mut guard = FlapGuard::default();
+        assert!(guard.observe(IP, true, &policy).is_err());
+        assert!(guard.observe("198.51.100.8", true, &policy).is_err());
+        assert_eq!(
+            guard.pending().map(|(ip, count, _)| (ip, count)),
+            Some(("198.51.100.8", 1))
+        );
+        assert!(guard.observe("198.51.100.8", true, &policy).is_ok());
+    }
+
+    #[test]
+    fn settling_drops_the_pending_change() {
+        let policy = policy(2, 0);
+        let mut guard = FlapGuard::default();
+        assert!(guard.observe(IP, true, &policy).is_err());
+        guard.settle();
+        assert!(guard.pending().is_none());
+        assert!(guard.observe(IP, true, &policy).is_err());
+    }
+
+    #[test]
+    fn update_interval_only_limits_modifications() {
+        let policy = StabilityPolicy {
+            min_update_interval: Duration::from_secs(300),
+            ..StabilityPolicy::default()
+        };
+        let mut guard = FlapGuard::default();
+        assert!(guard.observe(IP, true, &policy).is_ok());
+        guard.published();
+        assert!(guard.pending().is_none());
+
+        assert!(matches!(
+            guard.observe("198.51.100.8", true, &policy),
+            Err(SkipReason::TooSoon { remaining }) if remaining > Duration::from_secs(290)
+        ));
+        // Creating a missing record is never held back by the interval.
+        assert!(guard.observe("198.51.100.8", false, &policy).is_ok());
+
+        guard.restore_published(Duration::from_secs(301));
+        assert!(guard.observe("198.51.100.8", true, &policy).is_ok());
+    }
+
+    #[test]
+    fn publication_is_detected_against_an_earlier_copy() {
+        let mut guard = FlapGuard::default();
+        assert!(guard.observe(IP, true, &StabilityPolicy::default()).is_ok());
+        let earlier = guard.clone();
+        assert!(!guard.published_since(&earlier));
+        guard.published();
+        assert!(guard.published_since(&earlier));
+    }
+}
@@ -0,0 +1,267 @@
+use super::constants::{
+    API_DOMAIN_LIST, API_RECORD_CREATE, API_RECORD_INFO, API_RECORD_LIST, API_RECORD_MODIFY,
+    API_RECORD_REMARK, API_RECORD_REMOVE, API_RECORD_STATUS,
+};
+use std::fmt;
+
+/// A DNSPod API status code, as returned in the `status.code` field of every response.
+///
+/// Apart from a few common codes, the meaning of a code depends on the endpoint; codes
+/// that are unknown or ambiguous for an endpoint are kept verbatim as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiCode {
+    /// 1: The operation succeeded.
+    Success,
+    /// -1: Login failed; the token is wrong, expired or disabled.
+    LoginFailed,
+    /// -2: The API usage limit was exceeded.
+    RateLimited,
+    /// -3: Not a legitimate agent (agent APIs only).
+    NotAgent,
+    /// -4: Not under this agent (agent APIs only).
+    NotUnderAgent,
+    /// -7: No permission to use this API.
+    NoPermission,
+    /// -8: Too many failed logins; the account is temporarily banned.
+    TooManyLoginFailures,
+    /// -15: The domain has been banned.
+    DomainBanned,
+    /// -99: The function is temporarily unavailable.
+    ServicePaused,
+    /// 2: Only POST is allowed.
+    PostOnly,
+    /// 3: Unknown error.
+    UnknownError,
+    /// The domain does not exist or its ID is wrong (6 on the record endpoints).
+    DomainNotFound,
+    /// Not the domain owner, or no permission on the domain (9 on `Record.List`, 7 on the
+    /// other record endpoints).
+    NotDomainOwner,
+    /// The record ID is wrong, usually because the record was deleted or replaced (8).
+    RecordIdInvalid,
+    /// The domain list is empty (9 on `Domain.List`).
+    NoDomains,
+    /// The record list is empty (10 on `Record.List`).
+    NoRecords,
+    /// The domain is locked (21).
+    DomainLocked,
+    /// The sub-domain is not valid (22).
+    InvalidSubDomain,
+    /// The sub-domain has too many levels (23).
+    SubDomainTooDeep,
+    /// The plan's limit of round-robin records for this name was reached (25).
+    RecordLimitExceeded,
+    /// The record line is not valid (26).
+    InvalidRecordLine,
+    /// The record type is not valid (27).
+    InvalidRecordType,
+    /// A conflicting record exists; A, CNAME and URL records cannot coexist (31 on
+    /// `Record.Create`).
+    RecordConflict,
+    /// The record value is not valid (34).
+    InvalidRecordValue,
+    /// 83: The account is locked.
+    AccountLocked,
+    /// 85: The login was rejected as coming from an unusual location.
+    UnusualLogin,
+    /// The record was modified too frequently; reported with a generic code and
+    /// recognized by its message.
+    FrequentModification,
+    /// Any other code, kept verbatim.
+    Other(String),
+}
+
+impl ApiCode {
+    /// Maps a raw status code and message returned by `endpoint` (an API path such as
+    /// `/Record.List`) to a typed code.
+    pub fn parse(endpoint: &str, code: &str, message: &str) -> Self {
+        let code = code.trim();
+        let parsed = Self::common(code).unwrap_or_else(|| Self::for_endpoint(endpoint, code));
+
+        // DNSPod reports modification throttling with various codes; the message is the tell.
+        let lower = message.to_lowercase();
+        if parsed != ApiCode::Success && (message.contains("频繁") || lower.contains("frequent"))
+        {
+            return ApiCode::FrequentModification;
+        }
+        parsed
+    }
+
+    /// (Private) Codes that mean the same on every endpoint.
+    fn common(code: &str) -> Option<Self> {
+        Some(match code {
+            "1" => ApiCode::Success,
+            "-1" => ApiCode::LoginFailed,
+            "-2" => ApiCode::RateLimited,
+            "-3" => ApiCode::NotAgent,
+            "-4" => ApiCode::NotUnderAgent,
+            "-7" => ApiCode::NoPermission,
+            "-8" => ApiCode::TooManyLoginFailures,
+            "-15" => ApiCode::DomainBanned,
+            "-99" => ApiCode::ServicePaused,
+            "2" => ApiCode::PostOnly,
+            "3" => ApiCode::UnknownError,
+            "83" => ApiCode::AccountLocked,
+            "85" => ApiCode::UnusualLogin,
+            _ => return None,
+        })
+    }
+
+    /// (Private) Codes documented for one endpoint, e.g. 6 is an invalid offset on
+    /// `Domain.List` but an unknown domain on `Record.List`.
+    fn for_endpoint(endpoint: &str, code: &str) -> Self {
+        match (endpoint, code) {
+            (API_DOMAIN_LIST, "9") => ApiCode::NoDomains,
+            (API_RECORD_LIST, "6") => ApiCode::DomainNotFound,
+            (API_RECORD_LIST, "9") => ApiCode::NotDomainOwner,
+            (API_RECORD_LIST, "10") => ApiCode::NoRecords,
+            (
+                API_RECORD_MODIFY | API_RECORD_REMOVE | API_RECORD_STATUS | API_RECORD_INFO
+                | API_RECORD_REMARK,
+                "6",
+            ) => ApiCode::DomainNotFound,
+            (
+                API_RECORD_CREATE | API_RECORD_MODIFY | API_RECORD_REMOVE | API_RECORD_STATUS
+                | API_RECORD_INFO,
+                "7",
+            ) => ApiCode::NotDomainOwner,
+            (
+                API_RECORD_MODIFY | API_RECORD_REMOVE | API_RECORD_STATUS | API_RECORD_INFO
+                | API_RECORD_REMARK,
+                "8",
+            ) => ApiCode::RecordIdInvalid,
+            (
+                API_RECORD_CREATE | API_RECORD_MODIFY | API_RECORD_REMOVE | API_RECORD_STATUS
+                | API_RECORD_INFO,
+                "21",
+            ) => ApiCode::DomainLocked,
+            (API_RECORD_CREATE | API_RECORD_MODIFY, "22") => ApiCode::InvalidSubDomain,
+            (API_RECORD_CREATE | API_RECORD_MODIFY, "23") => ApiCode::SubDomainTooDeep,
+            (API_RECORD_CREATE | API_RECORD_MODIFY, "25") => ApiCode::RecordLimitExceeded,
+            (API_RECORD_CREATE | API_RECORD_MODIFY, "26") => ApiCode::InvalidRecordLine,
+            (API_RECORD_CREATE | API_RECORD_MODIFY, "27") => ApiCode::InvalidRecordType,
+            (API_RECORD_CREATE, "31") => ApiCode::RecordConflict,
+            (API_RECORD_CREATE | API_RECORD_MODIFY, "34") => ApiCode::InvalidRecordValue,
+            (_, other) => ApiCode::Other(other.to_string()),
+        }
+    }
+
+    /// Returns true for the success code.
+    pub fn is_success(&self) -> bool {
+        *self == ApiCode::Success
+    }
+}
+
+impl fmt::Display for ApiCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            ApiCode::Success => "success",
+            ApiCode::LoginFailed => "login failed",
+            ApiCode::RateLimited => "API usage limit exceeded",
+            ApiCode::NotAgent => "not an agent",
+            ApiCode::NotUnderAgent => "not under this agent",
+            ApiCode::NoPermission => "no permission for this API",
+            ApiCode::TooManyLoginFailures => "too many failed logins",
+            ApiCode::DomainBanned => "domain banned",
+            ApiCode::ServicePaused => "service paused",
+            ApiCode::PostOnly => "POST only",
+            ApiCode::UnknownError => "unknown error",
+            ApiCode::DomainNotFound => "domain not found",
+            ApiCode::NotDomainOwner => "not the domain owner",
+            ApiCode::RecordIdInvalid => "invalid record ID",
+            ApiCode::NoDomains => "no domains",
+            ApiCode::NoRecords => "no records",
+            ApiCode::DomainLocked => "domain locked",
+            ApiCode::InvalidSubDomain => "invalid sub-domain",
+            ApiCode::SubDomainTooDeep => "sub-domain too deep",
+            ApiCode::RecordLimitExceeded => "record limit exceeded",
+            ApiCode::InvalidRecordLine => "invalid record line",
+            ApiCode::InvalidRecordType => "invalid record type",
+            ApiCode::RecordConflict => "conflicting record",
+            ApiCode::InvalidRecordValue => "invalid record value",
+            ApiCode::AccountLocked => "account locked",
+            ApiCode::UnusualLogin => "unusual login location",
+            ApiCode::FrequentModification => "modified too frequently",
+            ApiCode::Other(_) => "unrecognized code",
+        };
+        f.write_str(description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DdnsError;
+
+    #[test]
+    fn same_code_depends_on_endpoint() {
+        assert_eq!(
+            ApiCode::parse(API_RECORD_LIST, "6", ""),
+            ApiCode::DomainNotFound
+        );
+        assert_eq!(
+            ApiCode::parse(API_DOMAIN_LIST, "6", ""),
+            ApiCode::Other("6".to_string())
+        );
+        assert_eq!(
+            ApiCode::parse(API_DOMAIN_LIST, "7", ""),
+            ApiCode::Other("7".to_string())
+        );
+        assert_eq!(ApiCode::parse(API_DOMAIN_LIST, "9", ""), ApiCode::NoDomains);
+        assert_eq!(
+            ApiCode::parse(API_RECORD_LIST, "9", ""),
+            ApiCode::NotDomainOwner
+        );
+    }
+
+    #[test]
+    fn create_codes_32_and_33_are_not_record_limits() {
+        for code in ["32", "33"] {
+            assert_eq!(
+                ApiCode::parse(API_RECORD_CREATE, code, ""),
+                ApiCode::Other(code.to_string())
+            );
+        }
+        assert_eq!(
+            ApiCode::parse(API_RECORD_CREATE, "25", ""),
+            ApiCode::RecordLimitExceeded
+        );
+        assert_eq!(
+            ApiCode::parse(API_RECORD_CREATE, "31", ""),
+            ApiCode::RecordConflict
+        );
+    }
+
+    #[test]
+    fn common_codes_and_unknown_endpoints() {
+        assert_eq!(
+            ApiCode::parse("/Whatever", " -1 ", ""),
+            ApiCode::LoginFailed
+        );
+        assert_eq!(
+            ApiCode::parse("/Whatever", "8", ""),
+            ApiCode::Other("8".to_string())
+        );
+        assert_eq!(
+            ApiCode::parse(API_RECORD_REMOVE, "1", "操作频繁"),
+            ApiCode::Success
+        );
+        assert_eq!(
+            ApiCode::parse(API_RECORD_MODIFY, "3", "记录修改过于频繁"),
+            ApiCode::FrequentModification
+        );
+    }
+
+    #[test]
+    fn error_message_keeps_the_raw_code() {
+        let err = DdnsError::Api {
+            code: ApiCode::parse(API_RECORD_INFO, "8", "Record id invalid"),
+            raw_code: "8".to_string(),
+            message: "Record id invalid".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "DNSPod API returned an error: Record id invalid (Code: 8, invalid record ID)"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::{ApiCode, RecordType};
 use thiserror::Error;
 
 /// Custom error types for the dnspod library.
@@ -9,12 +10,60 @@ pub enum DdnsError {
     #[error("Failed to parse JSON response: {0}")]
     JsonParse(#[from] serde_json::Error),
 
-    #[error("DNSPod API returned an error: {message} (Code: {code})")]
-    Api { code: String, message: String },
+    #[error("DNSPod API returned an error: {message} (Code: {raw_code}, {code})")]
+    Api {
+        code: ApiCode,
+        /// The code exactly as DNSPod returned it.
+        raw_code: String,
+        message: String,
+    },
 
     #[error("The provided IP address is not a valid IPv4 address: {0}")]
     InvalidIpFormat(String),
 
+    #[error(
+        "DNSPod is throttling this account; API calls are paused for another {remaining_secs}s"
+    )]
+    Throttled { remaining_secs: u64 },
+
+    #[error(
+        "Record {record_id} is not managed by this client (remark: '{remark}'); refusing to modify it"
+    )]
+    ForeignRecord { record_id: String, remark: String },
+
+    #[error(
+        "{record_type} record {record_id} ('{value}') at '{name}' conflicts with its A/AAAA records; {hint}"
+    )]
+    ConflictingRecord {
+        name: String,
+        record_id: String,
+        record_type: RecordType,
+        value: String,
+        hint: &'static str,
+    },
+
+    #[error("{failed} record(s) could not be released ({released} were): {source}")]
+    ReleaseFailed {
+        released: usize,
+        failed: usize,
+        source: Box<DdnsError>,
+    },
+
+    #[error("Address {ip} was rejected by the address policy ({rule})")]
+    AddressRejected { ip: String, rule: String },
+
+    #[error("Failed to extract an IP address from the response: {0}")]
+    IpExtraction(String),
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("'{name}' is not a valid domain name: {reason}")]
+    InvalidName { name: String, reason: String },
+
+    #[error("No domain in this DNSPod account manages '{0}'")]
+    ZoneNotFound(String),
+
     #[error("Failed to decode DNSPod API response. Body: '{body}'. Original error: {source}")]
     ApiResponseDecode {
         body: String,
@@ -23,5 +72,80 @@ pub enum DdnsError {
     },
 }
 
+/// How the daemon should react to an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// A transient failure; trying again later may succeed.
+    Retryable,
+    /// The request can never succeed as-is, but other operations may.
+    Permanent,
+    /// The configuration (token, domain, options) is wrong, or the account or domain is
+    /// locked; retrying only hammers the API.
+    Configuration,
+}
+
+impl DdnsError {
+    /// Classifies the error as retryable, permanent or a configuration error.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            DdnsError::Network(_)
+            | DdnsError::JsonParse(_)
+            | DdnsError::ApiResponseDecode { .. }
+            | DdnsError::InvalidIpFormat(_)
+            | DdnsError::IpExtraction(_)
+            | DdnsError::AddressRejected { .. }
+            | DdnsError::Throttled { .. } => ErrorClass::Retryable,
+            DdnsError::Config(_)
+            | DdnsError::InvalidName { .. }
+            | DdnsError::ZoneNotFound(_)
+            | DdnsError::ConflictingRecord { .. } => ErrorClass::Configuration,
+            DdnsError::ForeignRecord { .. } => ErrorClass::Permanent,
+            DdnsError::ReleaseFailed { source, .. } => source.class(),
+            DdnsError::Api { code, .. } => match code {
+                ApiCode::LoginFailed
+                | ApiCode::NotAgent
+                | ApiCode::NotUnderAgent
+                | ApiCode::NoPermission
+                | ApiCode::DomainNotFound
+                | ApiCode::NotDomainOwner
+                | ApiCode::InvalidSubDomain
+                | ApiCode::SubDomainTooDeep
+                | ApiCode::InvalidRecordLine
+                | ApiCode::InvalidRecordType
+                | ApiCode::TooManyLoginFailures
+                | ApiCode::DomainBanned
+                | ApiCode::DomainLocked
+                | ApiCode::AccountLocked
+                | ApiCode::UnusualLogin => ErrorClass::Configuration,
+                ApiCode::PostOnly
+                | ApiCode::RecordLimitExceeded
+                | ApiCode::RecordConflict
+                | ApiCode::InvalidRecordValue => ErrorClass::Permanent,
+                ApiCode::Success
+                | ApiCode::RateLimited
+                | ApiCode::FrequentModification
+                | ApiCode::ServicePaused
+                | ApiCode::UnknownError
+                | ApiCode::RecordIdInvalid
+                | ApiCode::NoRecords
+                | ApiCode::NoDomains
+                | ApiCode::Other(_) => ErrorClass::Retryable,
+            },
+        }
+    }
+
+    /// Returns true if DNSPod asked us to slow down.
+    pub fn is_throttled(&self) -> bool {
+        matches!(
+            self,
+            DdnsError::Throttled { .. }
+                | DdnsError::Api {
+                    code: ApiCode::RateLimited | ApiCode::FrequentModification,
+                    ..
+                }
+        )
+    }
+}
+
 /// A convenience type alias for `Result` with our custom error type.
 pub type Result<T> = std::result::Result<T, DdnsError>;
@@ -1,11 +1,21 @@
 pub mod core;
 pub mod error;
 pub mod notify;
+pub mod policy;
 pub mod probe;
+pub mod source;
 pub mod utils;
+pub mod watch;
 
 // Export API
-pub use core::DnspodClient;
-pub use error::{DdnsError, Result};
+pub use core::{
+    ApiCode, DnspodClient, DuplicateStrategy, OutageAction, Ownership, RateLimiter, Record,
+    RecordPage, RecordQuery, RecordSpec, RecordType, ShutdownAction, SkipReason, StabilityPolicy,
+    StateStore, UpdateOutcome,
+};
+pub use error::{DdnsError, ErrorClass, Result};
 pub use notify::HttpClient;
-pub use probe::{NetworkProbe, NetworkStatus};
+pub use policy::{AddressPolicy, Cidr};
+pub use probe::{NetworkProbe, NetworkStatus, has_route};
+pub use source::{CloudProvider, Extractor, HttpSource, IpSource, MetadataSource};
+pub use watch::NetworkWatcher;
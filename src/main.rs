@@ -1,41 +1,55 @@
 mod args;
-mod core;
-mod error;
-mod notify;
-mod probe;
-mod utils;
 
 use anyhow::Result;
-use args::Args;
+use args::{Args, SourceKind, Uplink};
 use clap::Parser;
-use core::{API_BASE, DnspodClient};
-use notify::HttpClient;
-use probe::{NetworkProbe, NetworkStatus};
+use dnspod::core::{API_BASE, DEFAULT_RECORD_LINE};
+use dnspod::{
+    AddressPolicy, DdnsError, DnspodClient, ErrorClass, Extractor, HttpClient, HttpSource,
+    IpSource, MetadataSource, NetworkProbe, NetworkStatus, NetworkWatcher, Ownership, RateLimiter,
+    ShutdownAction, StabilityPolicy, StateStore, UpdateOutcome, has_route,
+};
 use reqwest::Client;
+use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::process::ExitCode;
 use std::sync::Arc;
 use tokio::task::JoinHandle;
-use tokio::time::{self, Duration};
+use tokio::time::{self, Duration, Instant};
 use tracing::{debug, error, info, trace, warn};
 use tracing_subscriber::EnvFilter;
 
-// The single, dual-stack-aware IP detection service.
-const IP_SERVICE_URL: &str = "https://test.ipw.cn";
+/// The first pause after DNSPod reports throttling; doubled for every further throttled cycle.
+const THROTTLE_BACKOFF_BASE: Duration = Duration::from_secs(60);
+/// The longest pause between cycles while throttled.
+const THROTTLE_BACKOFF_MAX: Duration = Duration::from_secs(3600);
 
-/// Asynchronously gets the public IP address using a pre-configured client.
-async fn get_public_ip(client: &Client) -> Result<String> {
-    let response = client
-        .get(IP_SERVICE_URL)
-        .send()
-        .await?
-        .error_for_status()?;
+/// Exit status when a record could not be updated, e.g. because DNSPod was unreachable.
+const EXIT_UPDATE_FAILED: u8 = 1;
+/// Exit status when DNSPod rejected the configuration (bad token, unknown domain, ...).
+const EXIT_CONFIGURATION: u8 = 2;
+/// Exit status when a given address was rejected by the address policy.
+const EXIT_ADDRESS_REJECTED: u8 = 3;
+/// Exit status when a change was held back by flap protection.
+const EXIT_HELD_BACK: u8 = 4;
 
-    Ok(response.text().await?.trim().to_string())
+/// Asynchronously gets the public IP address of the expected family using a pre-configured client.
+async fn get_public_ip(client: &Client, source: &IpSource, want_v6: bool) -> Result<String> {
+    let ip = source.fetch(client, want_v6).await?;
+    if ip.is_ipv6() != want_v6 {
+        anyhow::bail!(
+            "{} returned '{}', which is not an {} address",
+            source,
+            ip,
+            if want_v6 { "IPv6" } else { "IPv4" }
+        );
+    }
+    Ok(ip.to_string())
 }
 
 /// The main application entry point.
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     // Initialize structured logging based on environment or a default filter.
     let filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("info,ddns=info,dnspod=info"));
@@ -50,157 +64,839 @@ async fn main() -> Result<()> {
     // Parse command-line arguments.
     let args = Args::parse();
 
-    // The DnspodClient is mandatory and is always created.
-    //    It's wrapped in an Arc for safe and efficient sharing across async tasks.
-    let dnspod_client =
-        Arc::new(DnspodClient::new(args.token, args.domain, args.sub_domain).await?);
-    info!("✅ DNSPod client configured.");
+    // Every detected address is checked against this policy before it reaches DNSPod.
+    let mut policy = if args.no_default_deny {
+        AddressPolicy::permissive()
+    } else {
+        AddressPolicy::default()
+    };
+    for &cidr in &args.allow_cidrs {
+        policy = policy.allow(cidr);
+    }
+    for &cidr in &args.deny_cidrs {
+        policy = policy.deny(cidr);
+    }
+
+    if args.on_shutdown == ShutdownAction::Park && args.park_ip.is_none() && args.park_ip6.is_none()
+    {
+        anyhow::bail!("--on-shutdown park needs --park-ip or --park-ip6");
+    }
+
+    // Addresses given on the command line skip detection, but not the policy.
+    let explicit: Vec<IpAddr> = args
+        .ip
+        .map(IpAddr::V4)
+        .into_iter()
+        .chain(args.ip6.map(IpAddr::V6))
+        .collect();
+    for ip in &explicit {
+        if let Err(e) = policy.check(ip) {
+            error!("⛔ Refusing to publish '{}': {}", ip, e);
+            return Ok(ExitCode::from(EXIT_ADDRESS_REJECTED));
+        }
+    }
+
+    // One target per uplink, or a single unbound target for the configured sub-domain.
+    let uplinks = if args.uplinks.is_empty() {
+        vec![Uplink::default()]
+    } else {
+        args.uplinks.clone()
+    };
+    // All records share the account, so they share one API rate limiter.
+    let limiter = Arc::new(RateLimiter::new(args.api_rate_limit, args.api_burst));
+    // Likewise, all records share one state file.
+    let store = args
+        .state_file
+        .as_ref()
+        .map(|path| Arc::new(StateStore::new(path, Duration::from_secs(args.state_ttl))));
+    let mut targets = Vec::with_capacity(uplinks.len());
+    for uplink in uplinks {
+        targets.push(Target::build(
+            &args,
+            &uplink,
+            policy.clone(),
+            limiter.clone(),
+            store.clone(),
+        )?);
+    }
+    let targets = Arc::new(targets);
+    info!(
+        "✅ DNSPod client configured for {} record(s).",
+        targets.len()
+    );
 
     // The Http Notify Client is optional. It's only created if a URL is provided.
-    let http_notify_client = if let Some(url) = args.http_url {
+    let http_notify_client = if let Some(url) = &args.http_url {
         info!("✅ HTTP API notifier configured.");
-        Some(Arc::new(HttpClient::new(url, args.http_token)?))
+        Some(Arc::new(
+            HttpClient::new(url.clone(), args.http_token.clone())?.with_dry_run(args.dry_run),
+        ))
     } else {
         info!("ℹ️ HTTP API notifier not configured.");
         None
     };
 
-    // Create dedicated HTTP clients for forcing IPv4 and IPv6 resolution.
-    let http_client_v4 = Client::builder()
-        .local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
-        .timeout(Duration::from_secs(10))
-        .build()?;
-
-    let http_client_v6 = if args.ipv6 {
-        Some(
-            Client::builder()
-                .local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED))
-                .timeout(Duration::from_secs(10))
-                .build()?,
-        )
-    } else {
-        None
-    };
+    if args.dry_run {
+        warn!("🧪 Dry run: DNSPod changes and notifications are only logged, not sent.");
+    }
+    if !explicit.is_empty() {
+        info!("🎯 Publishing the given address(es) without detection...");
+        warn_single_observation(&args);
+        let (report, status) =
+            publish_addresses(&targets, http_notify_client.as_deref(), &explicit).await;
+        log_summary(&report.changes, held_back(&targets), args.dry_run);
+        return Ok(ExitCode::from(status));
+    }
+
+    // Build the IP detection source from the configured URL, method, headers and extraction rule.
+    let mut http_source = HttpSource::new(args.ip_url.clone()).with_method(&args.ip_method)?;
+    for header in &args.ip_headers {
+        http_source = http_source.with_header(header)?;
+    }
+    if let Some(pattern) = &args.ip_regex {
+        http_source = http_source.with_extractor(Extractor::regex(pattern)?);
+    } else if let Some(pointer) = &args.ip_json_pointer {
+        http_source = http_source.with_extractor(Extractor::json_pointer(pointer)?);
+    }
+
+    // Each address family can be detected from a different source.
+    let ip_sources = Arc::new(IpSources {
+        v4: ip_source(args.ip_source, &http_source, args.metadata_url.as_deref())?,
+        v6: ip_source(args.ip6_source, &http_source, args.metadata_url.as_deref())?,
+    });
+    info!(
+        "🔎 Detecting IPv4 via {}{}",
+        ip_sources.v4,
+        if args.ipv6 {
+            format!(", IPv6 via {}", ip_sources.v6)
+        } else {
+            String::new()
+        }
+    );
 
     // Decide whether to run once or in a loop based on the 'interval' argument.
     if args.interval == 0 {
         info!("🚀 Running in single-shot mode...");
-        run_ddns_checks(
-            dnspod_client,
-            http_notify_client,
-            &http_client_v4,
-            http_client_v6.as_ref(),
-        )
-        .await;
+        if args.watch {
+            warn!("🚨 --watch has no effect with --interval 0; the check runs only once.");
+        }
+        warn_single_observation(&args);
+        let report = run_ddns_checks(targets.clone(), http_notify_client, ip_sources).await;
+        if let Some(reason) = &report.fatal {
+            error!("🛑 DNSPod rejected the configuration: {}", reason);
+        }
+        log_summary(&report.changes, held_back(&targets), args.dry_run);
         info!("✅ DDNS check finished.");
+        Ok(ExitCode::from(report.status))
     } else {
         info!(
             "🔄 Starting DDNS check loop, running every {} seconds.",
             args.interval
         );
-        let mut interval = time::interval(Duration::from_secs(args.interval));
-        let mut probe = NetworkProbe::new();
-        loop {
-            interval.tick().await;
+        let watcher = if args.watch {
+            match NetworkWatcher::spawn() {
+                Ok(watcher) => {
+                    info!("👀 Watching for network changes; polling remains as a safety net.");
+                    Some(watcher)
+                }
+                Err(e) => {
+                    warn!("🚨 Network change watching disabled: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Signals are only handled if records are to be released; otherwise they end the
+        // process right away as before.
+        let shutdown = async {
+            if args.on_shutdown == ShutdownAction::Keep {
+                std::future::pending().await
+            } else {
+                shutdown_signal().await
+            }
+        };
+        let exit = run_loop(
+            targets.clone(),
+            http_notify_client,
+            ip_sources,
+            watcher,
+            Duration::from_secs(args.interval),
+            shutdown,
+            Duration::from_secs(args.shutdown_timeout),
+        )
+        .await?;
+        Ok(match exit {
+            LoopExit::Shutdown(deadline) => {
+                release_targets(&targets, args.on_shutdown, deadline).await
+            }
+            LoopExit::Failed(status) => ExitCode::from(status),
+        })
+    }
+}
+
+/// Warns that a stability window cannot be satisfied by a single observation, unless the
+/// state file carries the observations over to the next run.
+fn warn_single_observation(args: &Args) {
+    if (args.stable_count > 1 || args.stable_secs > 0) && args.state_file.is_none() {
+        warn!(
+            "❗️ A stability window needs repeated observations; without --state-file, changes will be held back in single-shot mode."
+        );
+    }
+}
+
+/// Logs the changes of a single-shot run and how many were held back.
+fn log_summary(changes: &[String], held_back: u64, dry_run: bool) {
+    let verb = if dry_run { "planned" } else { "applied" };
+    if changes.is_empty() {
+        info!("📋 No changes {}.", verb);
+    } else {
+        info!("📋 {} change(s) {}:", changes.len(), verb);
+        for change in changes {
+            info!("   • {}", change);
+        }
+    }
+    if held_back > 0 {
+        info!("⏸️ {} change(s) held back by flap protection.", held_back);
+    }
+}
+
+/// The number of changes held back by flap protection across all targets.
+fn held_back(targets: &[Target]) -> u64 {
+    targets
+        .iter()
+        .map(|target| target.dnspod_client.skipped_changes())
+        .sum()
+}
+
+/// Publishes the given addresses to every target without detecting them.
+///
+/// Returns the changes made and the exit status: zero if every record now points to its
+/// address, otherwise the most severe `EXIT_*` status encountered.
+async fn publish_addresses(
+    targets: &[Target],
+    http_notify_client: Option<&HttpClient>,
+    addresses: &[IpAddr],
+) -> (CycleReport, u8) {
+    let mut report = CycleReport::default();
+    let mut status = 0;
+    for target in targets {
+        for ip in addresses {
+            let family = if ip.is_ipv6() { "IPv6" } else { "IPv4" };
+            let ip = ip.to_string();
+            match target.dnspod_client.update_if_needed(&ip).await {
+                Ok(UpdateOutcome::Skipped(reason)) => {
+                    warn!(
+                        "⏸️ [{}/{}] '{}' was not published: {}",
+                        target.label, family, ip, reason
+                    );
+                    status = status.max(EXIT_HELD_BACK);
+                }
+                Ok(outcome) => {
+                    if outcome.is_changed() {
+                        notify_change(http_notify_client, target, family, &ip).await;
+                        report.changes.push(format!(
+                            "{}/{}: {:?} -> {}",
+                            target.label, family, outcome, ip
+                        ));
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "🚨 [{}/{}] DNSPod update failed ({:?}): {}",
+                        target.label,
+                        family,
+                        e.class(),
+                        e
+                    );
+                    status = status.max(exit_status(&e));
+                }
+            }
+        }
+    }
+    (report, status)
+}
+
+/// The exit status of a single-shot run for a failed update.
+fn exit_status(e: &DdnsError) -> u8 {
+    match e {
+        DdnsError::AddressRejected { .. } => EXIT_ADDRESS_REJECTED,
+        _ if e.class() == ErrorClass::Configuration => EXIT_CONFIGURATION,
+        _ => EXIT_UPDATE_FAILED,
+    }
+}
+
+/// Sends the HTTP API notification for a published change; failures are only logged.
+async fn notify_change(
+    http_notify_client: Option<&HttpClient>,
+    target: &Target,
+    family: &str,
+    ip: &str,
+) {
+    if let Some(client) = http_notify_client {
+        if let Err(e) = client.notify_for(target.dnspod_client.name(), ip).await {
+            warn!(
+                "🚨 [{}/{}] HTTP API notification failed: {}",
+                target.label, family, e
+            );
+        }
+    }
+}
+
+/// Why the check loop stopped.
+enum LoopExit {
+    /// A shutdown signal arrived; the records are to be released by the deadline.
+    Shutdown(Instant),
+    /// DNSPod rejected the configuration; the process exits with this status.
+    Failed(u8),
+}
+
+/// Runs the checks of every target until DNSPod rejects the configuration or `shutdown`
+/// resolves.
+///
+/// On shutdown, a check cycle that is already running may finish within `shutdown_timeout`,
+/// so that no update is in flight while the records are released.
+async fn run_loop(
+    targets: Arc<Vec<Target>>,
+    http_notify_client: Option<Arc<HttpClient>>,
+    ip_sources: Arc<IpSources>,
+    mut watcher: Option<NetworkWatcher>,
+    every: Duration,
+    shutdown: impl Future<Output = std::io::Result<&'static str>>,
+    shutdown_timeout: Duration,
+) -> Result<LoopExit> {
+    tokio::pin!(shutdown);
+
+    // DNSPod may not be reachable yet at boot (e.g. before PPPoE is up); keep retrying
+    // until every record is loaded, but stop on a bad token or unknown domain.
+    let mut probe = NetworkProbe::new();
+    tokio::select! {
+        result = initialize_targets(&targets, &mut probe) => {
+            if let Err(e) = result {
+                return Ok(LoopExit::Failed(exit_status(&e)));
+            }
+        }
+        signal = &mut shutdown => return Ok(LoopExit::Shutdown(shutdown_deadline(signal, shutdown_timeout)?)),
+    }
+
+    let mut interval = time::interval(every);
+    let mut throttled_cycles: u32 = 0;
+    loop {
+        let wait = async {
+            // Wake up on the next tick, or earlier if the network changed.
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = network_changed(watcher.as_mut()) => {
+                    info!("🔔 Network change detected. Running checks now.");
+                    interval.reset();
+                }
+            }
 
             // 1. Wait for the core service to be healthy.
             //    This will block with exponential backoff until the service is reachable.
-            let status = probe.wait_for_service(API_BASE).await;
+            probe.wait_for_service(API_BASE).await
+        };
+        let status = tokio::select! {
+            status = wait => status,
+            signal = &mut shutdown => return Ok(LoopExit::Shutdown(shutdown_deadline(signal, shutdown_timeout)?)),
+        };
 
-            // 2. Log a message about the service status.
-            if status == NetworkStatus::JustRecovered {
-                info!(
-                    "🌐 DNSPod service at '{}' has recovered. Proceeding with checks.",
-                    API_BASE
-                );
-            } else {
-                trace!("DNSPod service is online. Proceeding with checks.");
+        // 2. Log a message about the service status.
+        if status == NetworkStatus::JustRecovered {
+            info!(
+                "🌐 DNSPod service at '{}' has recovered. Proceeding with checks.",
+                API_BASE
+            );
+        } else {
+            trace!("DNSPod service is online. Proceeding with checks.");
+        }
+
+        // 3. Run the DDNS checks for IPv4/IPv6 now that we know the service is up. They cover
+        //    every network change reported so far, so those do not trigger another run.
+        if let Some(watcher) = watcher.as_mut() {
+            watcher.mark_seen();
+        }
+        let cycle = run_ddns_checks(
+            targets.clone(),
+            http_notify_client.clone(),
+            ip_sources.clone(),
+        );
+        tokio::pin!(cycle);
+        let report = tokio::select! {
+            report = &mut cycle => report,
+            signal = &mut shutdown => {
+                let deadline = shutdown_deadline(signal, shutdown_timeout)?;
+                info!("⏳ Letting the running check finish first...");
+                if time::timeout_at(deadline, cycle).await.is_err() {
+                    warn!("⏰ The running check did not finish in time.");
+                }
+                return Ok(LoopExit::Shutdown(deadline));
             }
+        };
 
-            // 3. Run the DDNS checks for IPv4/IPv6 now that we know the service is up.
-            run_ddns_checks(
-                dnspod_client.clone(),
-                http_notify_client.clone(),
-                &http_client_v4,
-                http_client_v6.as_ref(),
-            )
-            .await;
+        // 4. Stop on configuration errors instead of hammering the API with bad credentials,
+        //    and back off exponentially while DNSPod is throttling us.
+        if let Some(reason) = report.fatal {
+            error!("🛑 DNSPod rejected the configuration, stopping: {}", reason);
+            return Ok(LoopExit::Failed(EXIT_CONFIGURATION));
+        }
+        if report.throttled {
+            throttled_cycles += 1;
+            let backoff = THROTTLE_BACKOFF_BASE
+                .saturating_mul(2u32.saturating_pow(throttled_cycles - 1))
+                .min(THROTTLE_BACKOFF_MAX);
+            warn!(
+                "🐢 DNSPod is throttling requests. Backing off for {:?} before the next check.",
+                backoff
+            );
+            tokio::select! {
+                _ = time::sleep(backoff) => {}
+                signal = &mut shutdown => return Ok(LoopExit::Shutdown(shutdown_deadline(signal, shutdown_timeout)?)),
+            }
+            interval.reset();
+        } else {
+            throttled_cycles = 0;
+        }
+    }
+}
+
+/// Resolves with the name of the signal once SIGTERM or SIGINT is received.
+async fn shutdown_signal() -> std::io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = terminate.recv() => Ok("SIGTERM"),
+            result = tokio::signal::ctrl_c() => result.map(|()| "SIGINT"),
         }
     }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        Ok("Ctrl-C")
+    }
+}
 
+/// Logs the received signal and returns the deadline for shutting down.
+fn shutdown_deadline(signal: std::io::Result<&'static str>, timeout: Duration) -> Result<Instant> {
+    info!("👋 Received {}. Shutting down...", signal?);
+    Ok(Instant::now() + timeout)
+}
+
+/// Deletes or parks the records of every target, giving up at `deadline`.
+async fn release_targets(
+    targets: &[Target],
+    action: ShutdownAction,
+    deadline: Instant,
+) -> ExitCode {
+    info!("🧹 Releasing records ({})...", action);
+    let release = async {
+        let mut status = ExitCode::SUCCESS;
+        for target in targets {
+            match target.dnspod_client.shutdown().await {
+                Ok(count) => info!("✅ [{}] {} record(s) released.", target.label, count),
+                Err(e) => {
+                    error!("🚨 [{}] Could not release records: {}", target.label, e);
+                    status = ExitCode::from(EXIT_UPDATE_FAILED);
+                }
+            }
+        }
+        status
+    };
+    match time::timeout_at(deadline, release).await {
+        Ok(status) => status,
+        Err(_) => {
+            error!("⏰ Shutdown timed out; some records may not have been released.");
+            ExitCode::from(EXIT_UPDATE_FAILED)
+        }
+    }
+}
+
+/// Loads the records of every target, retrying transient failures with the probe's backoff.
+///
+/// Like the check loop, this stops on configuration errors only. A target that failed
+/// permanently is left to the checks, which load its records on first use.
+async fn initialize_targets(
+    targets: &[Target],
+    probe: &mut NetworkProbe,
+) -> std::result::Result<(), DdnsError> {
+    for target in targets {
+        while !target.dnspod_client.is_initialized() {
+            probe.wait_for_service(API_BASE).await;
+            match target.dnspod_client.initialize().await {
+                Ok(()) => info!("✅ [{}] Records loaded from DNSPod.", target.label),
+                Err(e) if e.class() == ErrorClass::Configuration => {
+                    error!(
+                        "🛑 [{}] DNSPod rejected the configuration: {}",
+                        target.label, e
+                    );
+                    return Err(e);
+                }
+                Err(e) if e.class() == ErrorClass::Permanent => {
+                    warn!(
+                        "🚨 [{}] DNSPod initialization failed permanently: {}",
+                        target.label, e
+                    );
+                    break;
+                }
+                Err(e) => {
+                    probe
+                        .report_failure(
+                            API_BASE,
+                            format!("[{}] Initialization failed: {}", target.label, e),
+                        )
+                        .await
+                }
+            }
+        }
+    }
     Ok(())
 }
 
-/// Runs the DDNS checks for IPv4 and, if enabled, IPv6 concurrently.
+/// Resolves on the next network change, or never if no watcher is running.
+async fn network_changed(watcher: Option<&mut NetworkWatcher>) {
+    match watcher {
+        Some(watcher) => watcher.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// What a check cycle learned about how DNSPod treats our requests.
+#[derive(Debug, Default)]
+struct CycleReport {
+    /// Set if DNSPod rejected the configuration (bad token, unknown domain, ...).
+    fatal: Option<String>,
+    /// Set if DNSPod asked us to slow down.
+    throttled: bool,
+    /// The changes published (or, in a dry run, planned) during the cycle.
+    changes: Vec<String>,
+    /// The most severe `EXIT_*` status of the cycle, zero if every record is up to date.
+    status: u8,
+}
+
+/// What checking one address family of one target did.
+#[derive(Debug, Default)]
+struct FamilyReport {
+    /// The DNSPod error, if any, so the caller can decide how to react.
+    error: Option<DdnsError>,
+    /// A description of the change made to the record, if any.
+    change: Option<String>,
+    /// The `EXIT_*` status of the check, zero if the record is up to date.
+    status: u8,
+}
+
+/// Runs the DDNS checks for IPv4 and, if enabled, IPv6 of every target concurrently.
 async fn run_ddns_checks(
-    dnspod_client: Arc<DnspodClient>,
+    targets: Arc<Vec<Target>>,
     http_notify_client: Option<Arc<HttpClient>>,
-    http_client_v4: &Client,
-    http_client_v6: Option<&Client>,
-) {
+    ip_sources: Arc<IpSources>,
+) -> CycleReport {
     debug!("🔎 Starting scheduled DDNS check cycle...");
-    let mut tasks: Vec<JoinHandle<()>> = Vec::new();
-
-    // --- IPv4 Task ---
-    let dnspod_client_v4 = dnspod_client.clone();
-    let http_notify_client_v4 = http_notify_client.clone();
-    let http_client_v4 = http_client_v4.clone();
-    let v4_task = tokio::spawn(async move {
-        trace!("[IPv4] 🕵️ Starting check...");
-        match get_public_ip(&http_client_v4).await {
-            Ok(ip) => match dnspod_client_v4.update_if_needed(&ip).await {
-                Ok(was_updated) => {
-                    if was_updated {
-                        if let Some(client) = http_notify_client_v4 {
-                            if let Err(e) = client.notify(&ip).await {
-                                warn!("🚨 [IPv4] HTTP API notification failed: {}", e);
-                            }
-                        }
+    let mut tasks: Vec<JoinHandle<FamilyReport>> = Vec::new();
+
+    for index in 0..targets.len() {
+        // --- IPv4 Task ---
+        tasks.push(tokio::spawn(check_family(
+            targets.clone(),
+            index,
+            false,
+            http_notify_client.clone(),
+            ip_sources.clone(),
+        )));
+
+        // --- IPv6 Task ---
+        if targets[index].http_client_v6.is_some() {
+            tasks.push(tokio::spawn(check_family(
+                targets.clone(),
+                index,
+                true,
+                http_notify_client.clone(),
+                ip_sources.clone(),
+            )));
+        }
+    }
+
+    // Wait for all spawned tasks to complete.
+    let mut report = CycleReport::default();
+    for handle in tasks {
+        match handle.await {
+            Ok(family) => {
+                if let Some(e) = family.error {
+                    report.throttled |= e.is_throttled();
+                    if e.class() == ErrorClass::Configuration {
+                        report.fatal = Some(e.to_string());
                     }
                 }
-                Err(e) => warn!("🚨 [IPv4] DNSPod update failed: {}", e),
-            },
+                report.changes.extend(family.change);
+                report.status = report.status.max(family.status);
+            }
             Err(e) => {
-                trace!("[IPv4] 💨 Could not get public IPv4: {}", e);
+                error!("💥 A DDNS task panicked: {}", e);
+                report.status = report.status.max(EXIT_UPDATE_FAILED);
             }
         }
-    });
-    tasks.push(v4_task);
-
-    // --- IPv6 Task ---
-    if let Some(client_v6) = http_client_v6 {
-        let dnspod_client_v6 = dnspod_client.clone();
-        let http_notify_client_v6 = http_notify_client.clone();
-        let http_client_v6 = client_v6.clone();
-        let v6_task = tokio::spawn(async move {
-            trace!("[IPv6] 🕵️ Starting check...");
-            match get_public_ip(&http_client_v6).await {
-                Ok(ip) => match dnspod_client_v6.update_if_needed(&ip).await {
-                    Ok(was_updated) => {
-                        if was_updated {
-                            if let Some(client) = http_notify_client_v6 {
-                                if let Err(e) = client.notify(&ip).await {
-                                    warn!("🚨 [IPv6] HTTP API notification failed: {}", e);
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => warn!("🚨 [IPv6] DNSPod update failed: {}", e),
+    }
+
+    debug!("🏁 DDNS check cycle finished.");
+    report
+}
+
+/// Detects the public address of one family for one target and publishes it if needed.
+async fn check_family(
+    targets: Arc<Vec<Target>>,
+    index: usize,
+    is_v6: bool,
+    http_notify_client: Option<Arc<HttpClient>>,
+    ip_sources: Arc<IpSources>,
+) -> FamilyReport {
+    let target = &targets[index];
+    let family = if is_v6 { "IPv6" } else { "IPv4" };
+    let ip_source = if is_v6 {
+        &ip_sources.v6
+    } else {
+        &ip_sources.v4
+    };
+    let http_client = if is_v6 {
+        match &target.http_client_v6 {
+            Some(client) => client,
+            None => return FamilyReport::default(),
+        }
+    } else {
+        &target.http_client_v4
+    };
+
+    trace!("[{}/{}] 🕵️ Starting check...", target.label, family);
+    match get_public_ip(http_client, ip_source, is_v6).await {
+        Ok(ip) => match target.dnspod_client.update_if_needed(&ip).await {
+            Ok(outcome) => {
+                if outcome.is_changed() {
+                    notify_change(http_notify_client.as_deref(), target, family, &ip).await;
+                }
+                FamilyReport {
+                    error: None,
+                    change: outcome
+                        .is_changed()
+                        .then(|| format!("{}/{}: {:?} -> {}", target.label, family, outcome, ip)),
+                    status: if matches!(outcome, UpdateOutcome::Skipped(_)) {
+                        EXIT_HELD_BACK
+                    } else {
+                        0
+                    },
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "🚨 [{}/{}] DNSPod update failed ({:?}): {}",
+                    target.label,
+                    family,
+                    e.class(),
+                    e
+                );
+                FamilyReport {
+                    status: exit_status(&e),
+                    error: Some(e),
+                    change: None,
+                }
+            }
+        },
+        Err(e) => {
+            // A failing source alone says nothing about the address; only act on an outage
+            // once the family is gone from the host, and retry the source otherwise.
+            if target.has_route(is_v6) {
+                warn!(
+                    "🚨 [{}/{}] Could not get public {}: {}",
+                    target.label, family, family, e
+                );
+                return FamilyReport {
+                    status: EXIT_UPDATE_FAILED,
+                    ..FamilyReport::default()
+                };
+            }
+            info!(
+                "💨 [{}/{}] No {} route; the address is gone ({}).",
+                target.label, family, family, e
+            );
+            match target.dnspod_client.handle_missing_address(!is_v6).await {
+                Ok(handled) => FamilyReport {
+                    error: None,
+                    change: handled
+                        .then(|| format!("{}/{}: outage action applied", target.label, family)),
+                    status: 0,
                 },
                 Err(e) => {
-                    trace!("[IPv6] 💨 Could not get public IPv6: {}", e);
+                    warn!(
+                        "🚨 [{}/{}] Failed to pause the record: {}",
+                        target.label, family, e
+                    );
+                    FamilyReport {
+                        status: exit_status(&e),
+                        error: Some(e),
+                        change: None,
+                    }
                 }
             }
-        });
-        tasks.push(v6_task);
+        }
     }
+}
 
-    // Wait for all spawned tasks to complete.
-    for handle in tasks {
-        if let Err(e) = handle.await {
-            error!("💥 A DDNS task panicked: {}", e);
+/// The configured detection source of each address family.
+struct IpSources {
+    v4: IpSource,
+    v6: IpSource,
+}
+
+/// Builds the detection source selected for one address family.
+fn ip_source(
+    kind: SourceKind,
+    http_source: &HttpSource,
+    metadata_url: Option<&str>,
+) -> Result<IpSource> {
+    Ok(match kind.provider() {
+        Some(provider) => {
+            let mut source = MetadataSource::new(provider)?;
+            if let Some(url) = metadata_url {
+                source = source.with_base_url(url);
+            }
+            IpSource::Metadata(source)
+        }
+        None => IpSource::Http(http_source.clone()),
+    })
+}
+
+/// A record to keep up to date, together with the clients used to detect its address.
+struct Target {
+    label: String,
+    dnspod_client: Arc<DnspodClient>,
+    http_client_v4: Client,
+    http_client_v6: Option<Client>,
+    /// The local addresses and interface the detection clients are bound to.
+    address_v4: IpAddr,
+    address_v6: IpAddr,
+    interface: Option<String>,
+}
+
+impl Target {
+    /// Creates the DNSPod client and the detection clients bound to the uplink.
+    fn build(
+        args: &Args,
+        uplink: &Uplink,
+        policy: AddressPolicy,
+        limiter: Arc<RateLimiter>,
+        store: Option<Arc<StateStore>>,
+    ) -> Result<Self> {
+        let line = uplink
+            .line
+            .clone()
+            .unwrap_or_else(|| DEFAULT_RECORD_LINE.to_string());
+
+        // Either a full name whose zone is looked up, or a sub-domain of the given domain.
+        let dnspod_client = match &args.name {
+            Some(name) => {
+                let name = uplink.sub_domain.clone().unwrap_or_else(|| name.clone());
+                DnspodClient::new_for_name(args.token.clone(), name, line.clone())?
+            }
+            None => {
+                let domain = args.domain.clone().unwrap_or_default();
+                let sub_domain = uplink
+                    .sub_domain
+                    .clone()
+                    .or_else(|| args.sub_domain.clone())
+                    .unwrap_or_default();
+                DnspodClient::new_for_line(args.token.clone(), domain, sub_domain, line.clone())?
+            }
+        };
+        // Names are shown in Unicode, whatever form they were given in.
+        let mut label = dnspod_client.name().to_string();
+        if line != DEFAULT_RECORD_LINE {
+            label = format!("{}@{}", label, line);
+        }
+        if let Some(iface) = &uplink.interface {
+            label = format!("{} via {}", label, iface);
+        }
+
+        let mut dnspod_client = dnspod_client
+            .with_policy(policy)
+            .with_rate_limiter(limiter)
+            .with_stability(StabilityPolicy {
+                min_observations: args.stable_count.max(1),
+                min_stable: Duration::from_secs(args.stable_secs),
+                min_update_interval: Duration::from_secs(args.min_update_interval),
+            })
+            .with_outage_action(args.on_outage, Duration::from_secs(args.outage_grace))
+            .with_shutdown_action(args.on_shutdown)
+            .with_replace_conflicting(args.replace_conflicting)
+            .with_duplicate_strategy(args.duplicates)
+            .with_pinned_records(args.record_ids.clone())
+            .with_reconcile_interval(Duration::from_secs(args.reconcile_interval))
+            .with_dry_run(args.dry_run);
+        if let Some(store) = store {
+            dnspod_client = dnspod_client.with_state_store(store);
+        }
+        for park_ip in [args.park_ip.map(IpAddr::V4), args.park_ip6.map(IpAddr::V6)]
+            .into_iter()
+            .flatten()
+        {
+            dnspod_client = dnspod_client.with_parking_address(park_ip);
+        }
+        if let Some(owner_id) = &args.owner_id {
+            dnspod_client = dnspod_client
+                .with_ownership(Ownership::new(owner_id.clone()).adopt_foreign(args.adopt_foreign));
         }
+        let dnspod_client = Arc::new(dnspod_client);
+
+        // Create dedicated HTTP clients for forcing IPv4 and IPv6 resolution.
+        let address_v4 = uplink.address.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let address_v6 = uplink.address6.unwrap_or(IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        let http_client_v4 = detection_client(address_v4, uplink.interface.as_deref())?;
+        let http_client_v6 = if args.ipv6 {
+            Some(detection_client(address_v6, uplink.interface.as_deref())?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            label,
+            dnspod_client,
+            http_client_v4,
+            http_client_v6,
+            address_v4,
+            address_v6,
+            interface: uplink.interface.clone(),
+        })
     }
 
-    debug!("🏁 DDNS check cycle finished.");
+    /// Whether the uplink still has a route to the internet for the given family.
+    fn has_route(&self, is_v6: bool) -> bool {
+        let local_address = if is_v6 {
+            self.address_v6
+        } else {
+            self.address_v4
+        };
+        has_route(local_address, self.interface.as_deref())
+    }
+}
+
+/// Builds an HTTP client whose connections originate from `local_address`,
+/// optionally bound to a network interface.
+fn detection_client(local_address: IpAddr, interface: Option<&str>) -> Result<Client> {
+    let builder = Client::builder()
+        .local_address(local_address)
+        .timeout(Duration::from_secs(10));
+
+    #[cfg(target_os = "linux")]
+    let builder = match interface {
+        Some(iface) => builder.interface(iface),
+        None => builder,
+    };
+    #[cfg(not(target_os = "linux"))]
+    if let Some(iface) = interface {
+        anyhow::bail!(
+            "Binding to interface '{}' is only supported on Linux; use addr= instead",
+            iface
+        );
+    }
+
+    Ok(builder.build()?)
 }
@@ -0,0 +1,277 @@
+use crate::error::{DdnsError, Result};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Ranges that should never be published as a public address, with the reason shown in logs.
+const DEFAULT_DENY: &[(&str, &str)] = &[
+    ("0.0.0.0/8", "\"this\" network"),
+    ("10.0.0.0/8", "RFC 1918 private"),
+    ("100.64.0.0/10", "RFC 6598 CGNAT"),
+    ("127.0.0.0/8", "loopback"),
+    ("169.254.0.0/16", "link-local"),
+    ("172.16.0.0/12", "RFC 1918 private"),
+    ("192.0.0.0/24", "IETF protocol assignments"),
+    ("192.0.2.0/24", "documentation"),
+    ("192.168.0.0/16", "RFC 1918 private"),
+    ("198.18.0.0/15", "benchmarking"),
+    ("198.51.100.0/24", "documentation"),
+    ("203.0.113.0/24", "documentation"),
+    ("224.0.0.0/4", "multicast"),
+    ("240.0.0.0/4", "reserved"),
+    ("::/128", "unspecified"),
+    ("::1/128", "loopback"),
+    ("::ffff:0:0/96", "IPv4-mapped"),
+    ("100::/64", "discard-only"),
+    ("2001:db8::/32", "documentation"),
+    ("fc00::/7", "unique local (ULA)"),
+    ("fe80::/10", "link-local"),
+    ("ff00::/8", "multicast"),
+];
+
+/// An IPv4 or IPv6 network in CIDR notation, e.g. `100.64.0.0/10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Returns true if `ip` is of the same family and falls inside this network.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = DdnsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || DdnsError::Config(format!("Invalid CIDR '{}'", s));
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let network: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p.parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    cidr: Cidr,
+    reason: &'static str,
+}
+
+/// Decides whether a detected address may be published.
+///
+/// An address is rejected if it matches a deny rule, unless it also matches an
+/// allow rule: allow rules are exceptions that take precedence over deny rules.
+#[derive(Debug, Clone)]
+pub struct AddressPolicy {
+    allow: Vec<Cidr>,
+    deny: Vec<Rule>,
+}
+
+impl AddressPolicy {
+    /// A policy with no rules at all, which accepts every address.
+    pub fn permissive() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+
+    /// Adds an allow rule.
+    pub fn allow(mut self, cidr: Cidr) -> Self {
+        self.allow.push(cidr);
+        self
+    }
+
+    /// Adds a deny rule.
+    pub fn deny(mut self, cidr: Cidr) -> Self {
+        self.deny.push(Rule {
+            cidr,
+            reason: "configured deny rule",
+        });
+        self
+    }
+
+    /// Checks `ip` against the policy, returning `DdnsError::AddressRejected` if it is denied.
+    pub fn check(&self, ip: &IpAddr) -> Result<()> {
+        if self.allow.iter().any(|cidr| cidr.contains(ip)) {
+            return Ok(());
+        }
+        match self.deny.iter().find(|rule| rule.cidr.contains(ip)) {
+            Some(rule) => Err(DdnsError::AddressRejected {
+                ip: ip.to_string(),
+                rule: format!("{}, {}", rule.cidr, rule.reason),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for AddressPolicy {
+    /// The default policy denies private, CGNAT, loopback, link-local,
+    /// documentation, multicast and other non-routable ranges.
+    fn default() -> Self {
+        let deny = DEFAULT_DENY
+            .iter()
+            .map(|(cidr, reason)| Rule {
+                cidr: cidr.parse().expect("built-in CIDR must be valid"),
+                reason,
+            })
+            .collect();
+        Self {
+            allow: Vec::new(),
+            deny,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_cidrs_and_single_addresses() {
+        assert_eq!(cidr("100.64.0.0/10").to_string(), "100.64.0.0/10");
+        assert_eq!(cidr(" 198.51.100.7 ").to_string(), "198.51.100.7/32");
+        assert_eq!(cidr("2001:db8::1").to_string(), "2001:db8::1/128");
+        for invalid in [
+            "10.0.0.0/33",
+            "::/129",
+            "10.0.0.0/",
+            "10.0.0/8",
+            "host/8",
+            "",
+        ] {
+            assert!(
+                matches!(invalid.parse::<Cidr>(), Err(DdnsError::Config(_))),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn zero_prefix_matches_the_whole_family() {
+        assert!(cidr("0.0.0.0/0").contains(&ip("255.255.255.255")));
+        assert!(cidr("0.0.0.0/0").contains(&ip("0.0.0.0")));
+        assert!(!cidr("0.0.0.0/0").contains(&ip("::1")));
+        assert!(cidr("::/0").contains(&ip("ffff::1")));
+        assert!(!cidr("::/0").contains(&ip("198.51.100.7")));
+    }
+
+    #[test]
+    fn full_prefix_matches_one_address() {
+        assert!(cidr("198.51.100.7/32").contains(&ip("198.51.100.7")));
+        assert!(!cidr("198.51.100.7/32").contains(&ip("198.51.100.8")));
+        assert!(cidr("2001:db8::7/128").contains(&ip("2001:db8::7")));
+        assert!(!cidr("2001:db8::7/128").contains(&ip("2001:db8::8")));
+    }
+
+    #[test]
+    fn host_bits_of_the_network_are_ignored() {
+        assert!(cidr("10.1.2.3/8").contains(&ip("10.200.0.1")));
+        assert!(!cidr("10.1.2.3/8").contains(&ip("11.0.0.1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_are_ipv6() {
+        let mapped = ip("::ffff:198.51.100.7");
+        assert!(!cidr("198.51.100.0/24").contains(&mapped));
+        assert!(cidr("::ffff:0:0/96").contains(&mapped));
+
+        // Even a mapped public address is no valid AAAA value.
+        let err = AddressPolicy::default()
+            .check(&ip("::ffff:8.8.8.8"))
+            .unwrap_err();
+        assert!(err.to_string().contains("IPv4-mapped"), "{}", err);
+    }
+
+    #[test]
+    fn default_policy_denies_non_routable_ranges() {
+        let policy = AddressPolicy::default();
+        for denied in [
+            "10.1.2.3",
+            "100.64.0.1",
+            "203.0.113.7",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert!(
+                matches!(
+                    policy.check(&ip(denied)),
+                    Err(DdnsError::AddressRejected { .. })
+                ),
+                "{}",
+                denied
+            );
+        }
+        for allowed in ["8.8.8.8", "2400:3200::1"] {
+            assert!(policy.check(&ip(allowed)).is_ok(), "{}", allowed);
+        }
+    }
+
+    #[test]
+    fn allow_inside_deny_takes_precedence() {
+        let policy = AddressPolicy::permissive()
+            .deny(cidr("100.64.0.0/10"))
+            .allow(cidr("100.64.1.0/24"));
+        assert!(policy.check(&ip("100.64.1.9")).is_ok());
+        assert!(policy.check(&ip("100.64.2.9")).is_err());
+        assert!(policy.check(&ip("198.51.100.7")).is_ok());
+
+        // An allow rule also punches a hole into the default ranges.
+        let policy = AddressPolicy::default().allow(cidr("203.0.113.7"));
+        assert!(policy.check(&ip("203.0.113.7")).is_ok());
+        assert!(policy.check(&ip("203.0.113.8")).is_err());
+    }
+
+    #[test]
+    fn rejection_names_the_matching_rule() {
+        let err = AddressPolicy::permissive()
+            .deny(cidr("198.51.100.0/24"))
+            .check(&ip("198.51.100.7"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Address 198.51.100.7 was rejected by the address policy \
+             (198.51.100.0/24, configured deny rule)"
+        );
+    }
+}
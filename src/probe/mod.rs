@@ -1,3 +1,7 @@
+mod route;
+
+pub use route::has_route;
+
 use reqwest::Client;
 use std::time::Duration;
 use tokio::time;
@@ -72,6 +76,14 @@ impl NetworkProbe {
         }
     }
 
+    /// Reports a failure seen outside the probe, e.g. a failed API call while the
+    /// service answers probes, and sleeps with the same exponential backoff.
+    ///
+    /// The next successful `wait_for_service` then reports `JustRecovered`.
+    pub async fn report_failure(&mut self, service_url: &str, reason: String) {
+        self.handle_failure(service_url, reason).await;
+    }
+
     /// (Private) Handles a failed probe by logging and sleeping with backoff.
     async fn handle_failure(&mut self, service_url: &str, reason: String) {
         self.consecutive_failures += 1;
@@ -96,3 +108,9 @@ impl NetworkProbe {
         time::sleep(backoff_duration).await;
     }
 }
+
+impl Default for NetworkProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,103 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+use tracing::{debug, trace};
+
+/// Destinations used to look up the route of each family: DNSPod's public resolvers.
+/// Nothing is sent to them.
+const ROUTE_TARGET_V4: Ipv4Addr = Ipv4Addr::new(119, 29, 29, 29);
+const ROUTE_TARGET_V6: Ipv6Addr = Ipv6Addr::new(0x2402, 0x4e00, 0, 0, 0, 0, 0, 0);
+
+/// Checks whether the host still has a route to the internet for the family of
+/// `local_address`, optionally bound to a network interface.
+///
+/// Connecting a UDP socket only looks up the route in the kernel; no packet is sent.
+/// The family is gone if there is no route, or if the kernel would send from a source
+/// address that cannot reach the internet (loopback, link-local or, for IPv6, unique
+/// local). If the lookup itself fails for another reason, the family is assumed to be up.
+pub fn has_route(local_address: IpAddr, interface: Option<&str>) -> bool {
+    match route_source(local_address, interface) {
+        Ok(Some(source)) => {
+            trace!(
+                "Route from {} uses source address {}.",
+                local_address, source
+            );
+            is_routable(&source)
+        }
+        Ok(None) => false,
+        Err(e) => {
+            debug!("Could not look up the route from {}: {}", local_address, e);
+            true
+        }
+    }
+}
+
+/// (Private) The source address the kernel picks for internet traffic, or `None` if
+/// there is no route.
+fn route_source(local_address: IpAddr, interface: Option<&str>) -> io::Result<Option<IpAddr>> {
+    let socket = match UdpSocket::bind((local_address, 0)) {
+        Ok(socket) => socket,
+        // The configured source address is no longer assigned.
+        Err(e) if e.kind() == io::ErrorKind::AddrNotAvailable => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    #[cfg(target_os = "linux")]
+    if let Some(iface) = interface {
+        bind_to_device(&socket, iface)?;
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = interface;
+
+    let target = match local_address {
+        IpAddr::V4(_) => IpAddr::V4(ROUTE_TARGET_V4),
+        IpAddr::V6(_) => IpAddr::V6(ROUTE_TARGET_V6),
+    };
+    match socket.connect((target, 53)) {
+        Ok(()) => Ok(Some(socket.local_addr()?.ip())),
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NetworkUnreachable
+                    | io::ErrorKind::HostUnreachable
+                    | io::ErrorKind::AddrNotAvailable
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// (Private) Binds the socket to a network interface (SO_BINDTODEVICE).
+#[cfg(target_os = "linux")]
+fn bind_to_device(socket: &UdpSocket, iface: &str) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the option value points to `iface.len()` valid bytes for the whole call.
+    let rc = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            iface.as_ptr().cast(),
+            iface.len() as libc::socklen_t,
+        )
+    };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// (Private) Whether traffic from `source` can reach the internet, possibly through NAT.
+fn is_routable(source: &IpAddr) -> bool {
+    match source {
+        IpAddr::V4(v4) => !(v4.is_unspecified() || v4.is_loopback() || v4.is_link_local()),
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            let link_local = first & 0xffc0 == 0xfe80;
+            let unique_local = first & 0xfe00 == 0xfc00;
+            !(v6.is_unspecified() || v6.is_loopback() || link_local || unique_local)
+        }
+    }
+}
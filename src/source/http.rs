@@ -0,0 +1,244 @@
+use crate::error::{DdnsError, Result};
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Method};
+use serde_json::Value;
+use std::net::IpAddr;
+use tracing::{instrument, trace};
+
+/// Describes how the IP address is pulled out of a response body.
+#[derive(Debug, Clone, Default)]
+pub enum Extractor {
+    /// The whole body, with surrounding whitespace removed, is the address.
+    #[default]
+    Trim,
+    /// The first capture group of the regex (or the whole match if it has none).
+    Regex(Regex),
+    /// A JSON pointer (RFC 6901) into a JSON body, e.g. `/ip`.
+    JsonPointer(String),
+}
+
+impl Extractor {
+    /// Builds a regex extractor, validating the pattern up front.
+    pub fn regex(pattern: &str) -> Result<Self> {
+        Regex::new(pattern)
+            .map(Extractor::Regex)
+            .map_err(|e| DdnsError::Config(format!("Invalid IP regex '{}': {}", pattern, e)))
+    }
+
+    /// Builds a JSON pointer extractor, validating its syntax up front.
+    pub fn json_pointer(pointer: &str) -> Result<Self> {
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Err(DdnsError::Config(format!(
+                "Invalid JSON pointer '{}': it must be empty or start with '/'",
+                pointer
+            )));
+        }
+        Ok(Extractor::JsonPointer(pointer.to_string()))
+    }
+
+    /// Applies the extraction rule to a response body and parses the result.
+    pub fn extract(&self, body: &str) -> Result<IpAddr> {
+        let candidate = match self {
+            Extractor::Trim => body.trim().to_string(),
+            Extractor::Regex(re) => {
+                let caps = re.captures(body).ok_or_else(|| {
+                    DdnsError::IpExtraction(format!("Regex '{}' did not match the body", re))
+                })?;
+                caps.get(1)
+                    .or_else(|| caps.get(0))
+                    .map(|m| m.as_str().trim().to_string())
+                    .unwrap_or_default()
+            }
+            Extractor::JsonPointer(pointer) => {
+                let json: Value = serde_json::from_str(body).map_err(|e| {
+                    DdnsError::IpExtraction(format!("Response is not valid JSON: {}", e))
+                })?;
+                match json.pointer(pointer) {
+                    Some(Value::String(s)) => s.trim().to_string(),
+                    Some(other) => other.to_string(),
+                    None => {
+                        return Err(DdnsError::IpExtraction(format!(
+                            "JSON pointer '{}' not found in the response",
+                            pointer
+                        )));
+                    }
+                }
+            }
+        };
+
+        candidate.parse::<IpAddr>().map_err(|_| {
+            DdnsError::IpExtraction(format!(
+                "'{}' is not an IP address",
+                truncate(&candidate, 64)
+            ))
+        })
+    }
+}
+
+/// An HTTP endpoint that reports the caller's public IP address.
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    url: String,
+    method: Method,
+    headers: HeaderMap,
+    extractor: Extractor,
+}
+
+impl HttpSource {
+    /// Creates a source that sends a plain GET and trims the response body.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            method: Method::GET,
+            headers: HeaderMap::new(),
+            extractor: Extractor::Trim,
+        }
+    }
+
+    /// Sets the HTTP method, e.g. "GET" or "POST".
+    pub fn with_method(mut self, method: &str) -> Result<Self> {
+        self.method = method
+            .to_ascii_uppercase()
+            .parse::<Method>()
+            .map_err(|e| DdnsError::Config(format!("Invalid HTTP method '{}': {}", method, e)))?;
+        Ok(self)
+    }
+
+    /// Adds a request header given in "Name: Value" format.
+    pub fn with_header(mut self, header: &str) -> Result<Self> {
+        let (name, value) = header.split_once(':').ok_or_else(|| {
+            DdnsError::Config(format!(
+                "Invalid header '{}': expected \"Name: Value\"",
+                header
+            ))
+        })?;
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|e| DdnsError::Config(format!("Invalid header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|e| {
+            DdnsError::Config(format!("Invalid header value for '{}': {}", name, e))
+        })?;
+        self.headers.append(name, value);
+        Ok(self)
+    }
+
+    /// Sets the rule used to extract the address from the response body.
+    pub fn with_extractor(mut self, extractor: Extractor) -> Self {
+        self.extractor = extractor;
+        self
+    }
+
+    /// The URL this source queries.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Queries the endpoint with the given client and extracts the address.
+    #[instrument(skip(self, client), fields(url = %self.url))]
+    pub async fn fetch(&self, client: &Client) -> Result<IpAddr> {
+        let response = client
+            .request(self.method.clone(), &self.url)
+            .headers(self.headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let body = response.text().await?;
+        trace!("IP service responded with {} bytes", body.len());
+        self.extractor.extract(&body)
+    }
+}
+
+/// Shortens a body excerpt for log and error messages.
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((idx, _)) => &s[..idx],
+        None => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn trim_takes_the_whole_body() {
+        assert_eq!(
+            Extractor::Trim.extract(" 198.51.100.7\n").unwrap(),
+            ip("198.51.100.7")
+        );
+        assert!(Extractor::Trim.extract("<html>").is_err());
+    }
+
+    #[test]
+    fn regex_prefers_the_first_capture_group() {
+        let extractor = Extractor::regex(r"ip=(\S+)").unwrap();
+        assert_eq!(
+            extractor.extract("fl=1\nip=2001:db8::7\nts=1").unwrap(),
+            ip("2001:db8::7")
+        );
+    }
+
+    #[test]
+    fn regex_without_capture_group_uses_the_whole_match() {
+        let extractor = Extractor::regex(r"\d+\.\d+\.\d+\.\d+").unwrap();
+        assert_eq!(
+            extractor.extract("Your IP: 198.51.100.7.").unwrap(),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn regex_that_does_not_match_is_an_extraction_error() {
+        let extractor = Extractor::regex(r"ip=(\S+)").unwrap();
+        let err = extractor.extract("nothing here").unwrap_err();
+        assert!(matches!(err, DdnsError::IpExtraction(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn invalid_regex_is_a_config_error() {
+        assert!(matches!(
+            Extractor::regex("(unclosed"),
+            Err(DdnsError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn json_pointer_reads_nested_strings() {
+        let extractor = Extractor::json_pointer("/data/ip").unwrap();
+        assert_eq!(
+            extractor
+                .extract(r#"{"data": {"ip": " 198.51.100.7 "}}"#)
+                .unwrap(),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn json_pointer_to_a_number_is_not_an_address() {
+        let extractor = Extractor::json_pointer("/ip").unwrap();
+        let err = extractor.extract(r#"{"ip": 3325256711}"#).unwrap_err();
+        assert!(matches!(err, DdnsError::IpExtraction(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn json_pointer_errors() {
+        assert!(matches!(
+            Extractor::json_pointer("ip"),
+            Err(DdnsError::Config(_))
+        ));
+        let extractor = Extractor::json_pointer("/ip").unwrap();
+        assert!(matches!(
+            extractor.extract(r#"{"addr": "198.51.100.7"}"#),
+            Err(DdnsError::IpExtraction(_))
+        ));
+        assert!(matches!(
+            extractor.extract("198.51.100.7"),
+            Err(DdnsError::IpExtraction(_))
+        ));
+    }
+}
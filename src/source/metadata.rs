@@ -0,0 +1,273 @@
+use crate::error::{DdnsError, Result};
+use reqwest::{Client, StatusCode};
+use std::net::IpAddr;
+use std::time::Duration;
+use tracing::{debug, instrument, trace};
+
+/// How long a requested session token stays valid, in seconds.
+const TOKEN_TTL_SECS: &str = "21600";
+
+/// A cloud whose instance metadata service reports the instance's public address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    /// AWS EC2, using the IMDSv2 session token flow.
+    Aws,
+    /// Tencent Cloud CVM.
+    Tencent,
+    /// Alibaba Cloud ECS, using the hardened-mode token flow when available.
+    Alibaba,
+}
+
+/// Headers of a metadata service's session token flow.
+struct TokenFlow {
+    /// Header carrying the requested TTL on `PUT /latest/api/token`.
+    ttl_header: &'static str,
+    /// Header carrying the token on metadata requests.
+    token_header: &'static str,
+}
+
+impl CloudProvider {
+    /// The metadata service address reachable from inside an instance.
+    pub fn default_base_url(&self) -> &'static str {
+        match self {
+            CloudProvider::Aws => "http://169.254.169.254",
+            CloudProvider::Tencent => "http://metadata.tencentyun.com",
+            CloudProvider::Alibaba => "http://100.100.100.200",
+        }
+    }
+
+    /// Whether the metadata service reports a public IPv6 address.
+    pub fn supports_ipv6(&self) -> bool {
+        !self.paths(true).is_empty()
+    }
+
+    /// Metadata paths of the public address of the given family, tried in order.
+    fn paths(&self, want_v6: bool) -> &'static [&'static str] {
+        match (self, want_v6) {
+            (CloudProvider::Aws, false) => &["/latest/meta-data/public-ipv4"],
+            (CloudProvider::Aws, true) => &["/latest/meta-data/ipv6"],
+            (CloudProvider::Tencent, false) => &["/latest/meta-data/public-ipv4"],
+            // An elastic IP takes precedence over the instance's own public address.
+            (CloudProvider::Alibaba, false) => {
+                &["/latest/meta-data/eipv4", "/latest/meta-data/public-ipv4"]
+            }
+            _ => &[],
+        }
+    }
+
+    fn token_flow(&self) -> Option<TokenFlow> {
+        match self {
+            CloudProvider::Aws => Some(TokenFlow {
+                ttl_header: "X-aws-ec2-metadata-token-ttl-seconds",
+                token_header: "X-aws-ec2-metadata-token",
+            }),
+            CloudProvider::Alibaba => Some(TokenFlow {
+                ttl_header: "X-aliyun-ecs-metadata-token-ttl-seconds",
+                token_header: "X-aliyun-ecs-metadata-token",
+            }),
+            CloudProvider::Tencent => None,
+        }
+    }
+}
+
+/// Reads the public address from a cloud instance metadata service.
+///
+/// The service is local to the instance and answers for both families over whatever
+/// transport reaches it, so it is queried with its own unbound client rather than one
+/// bound to the family being detected.
+#[derive(Debug, Clone)]
+pub struct MetadataSource {
+    provider: CloudProvider,
+    base_url: String,
+    client: Client,
+}
+
+impl MetadataSource {
+    /// Creates a source that queries the provider's well-known metadata address.
+    pub fn new(provider: CloudProvider) -> Result<Self> {
+        Ok(Self {
+            provider,
+            base_url: provider.default_base_url().to_string(),
+            client: Client::builder()
+                .timeout(Duration::from_secs(5)) // The service is link-local
+                .build()?,
+        })
+    }
+
+    /// Overrides the metadata service address, e.g. to use a local stand-in server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The provider this source queries.
+    pub fn provider(&self) -> CloudProvider {
+        self.provider
+    }
+
+    /// Queries the metadata service for the public address of the given family.
+    #[instrument(skip(self), fields(provider = ?self.provider, base = %self.base_url))]
+    pub async fn fetch(&self, want_v6: bool) -> Result<IpAddr> {
+        let client = &self.client;
+        let family = if want_v6 { "IPv6" } else { "IPv4" };
+        let paths = self.provider.paths(want_v6);
+        if paths.is_empty() {
+            return Err(DdnsError::Config(format!(
+                "{:?} metadata does not report a public {} address",
+                self.provider, family
+            )));
+        }
+
+        let token = match self.provider.token_flow() {
+            Some(flow) => self
+                .session_token(client, &flow)
+                .await?
+                .map(|token| (flow.token_header, token)),
+            None => None,
+        };
+
+        for path in paths {
+            let mut request = client.get(format!("{}{}", self.base_url, path));
+            if let Some((header, token)) = &token {
+                request = request.header(*header, token);
+            }
+
+            let response = request.send().await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                trace!("Metadata path '{}' not found", path);
+                continue;
+            }
+            let body = response.error_for_status()?.text().await?;
+            trace!("Metadata service responded with {} bytes", body.len());
+
+            // Multi-valued entries (e.g. several IPv6 addresses) are newline separated.
+            let first = body.lines().next().unwrap_or_default().trim();
+            return first
+                .parse::<IpAddr>()
+                .map_err(|_| DdnsError::IpExtraction(format!("'{}' is not an IP address", first)));
+        }
+
+        Err(DdnsError::IpExtraction(format!(
+            "The instance has no public {} address in its metadata",
+            family
+        )))
+    }
+
+    /// (Private) Requests a session token, or returns `None` if the service does not require one.
+    async fn session_token(&self, client: &Client, flow: &TokenFlow) -> Result<Option<String>> {
+        let response = client
+            .put(format!("{}/latest/api/token", self.base_url))
+            .header(flow.ttl_header, TOKEN_TTL_SECS)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(Some(response.text().await?.trim().to_string()));
+        }
+        match status {
+            // Token flow not enabled or not supported; fall back to plain requests.
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED | StatusCode::FORBIDDEN => {
+                debug!(
+                    "Metadata token request returned {}; continuing without a token.",
+                    status
+                );
+                Ok(None)
+            }
+            _ => Err(DdnsError::IpExtraction(format!(
+                "Metadata token request failed with HTTP status {}",
+                status
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const TOKEN: &str = "test-token";
+
+    /// Serves a fake IMDSv2 on a local IPv4 listener and returns its base URL.
+    ///
+    /// `PUT /latest/api/token` hands out `TOKEN`; metadata paths answer from `entries`
+    /// only when the token is presented, and with 404 for unknown paths.
+    async fn serve(entries: &'static [(&'static str, &'static str)]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8_lossy(&request).to_ascii_lowercase();
+                let mut words = request.split_whitespace();
+                let (method, path) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+                let has_token = request.contains(&format!("x-aws-ec2-metadata-token: {}", TOKEN));
+
+                let (status, body) = match (method, path) {
+                    ("put", "/latest/api/token") => ("200 OK", TOKEN),
+                    ("get", _) if !has_token => ("401 Unauthorized", ""),
+                    ("get", path) => match entries.iter().find(|(p, _)| *p == path) {
+                        Some((_, body)) => ("200 OK", *body),
+                        None => ("404 Not Found", ""),
+                    },
+                    _ => ("405 Method Not Allowed", ""),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        base_url
+    }
+
+    #[tokio::test]
+    async fn fetches_ipv4_with_session_token() {
+        let base_url = serve(&[("/latest/meta-data/public-ipv4", "198.51.100.7\n")]).await;
+        let source = MetadataSource::new(CloudProvider::Aws)
+            .unwrap()
+            .with_base_url(base_url);
+
+        let ip = source.fetch(false).await.unwrap();
+
+        assert_eq!(ip, "198.51.100.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn fetches_ipv6_over_an_ipv4_endpoint() {
+        let base_url = serve(&[("/latest/meta-data/ipv6", "2001:db8::7\n2001:db8::8")]).await;
+        let source = MetadataSource::new(CloudProvider::Aws)
+            .unwrap()
+            .with_base_url(base_url);
+
+        let ip = source.fetch(true).await.unwrap();
+
+        assert_eq!(ip, "2001:db8::7".parse::<IpAddr>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn reports_a_missing_public_address() {
+        let base_url = serve(&[]).await;
+        let source = MetadataSource::new(CloudProvider::Aws)
+            .unwrap()
+            .with_base_url(base_url);
+
+        let err = source.fetch(false).await.unwrap_err();
+
+        assert!(matches!(err, DdnsError::IpExtraction(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,42 @@
+mod http;
+mod metadata;
+
+pub use http::{Extractor, HttpSource};
+pub use metadata::{CloudProvider, MetadataSource};
+
+use crate::error::Result;
+use reqwest::Client;
+use std::fmt;
+use std::net::IpAddr;
+
+/// The default, dual-stack-aware IP detection service.
+pub const DEFAULT_IP_SERVICE_URL: &str = "https://test.ipw.cn";
+
+/// Where the public address of one address family is detected.
+#[derive(Debug, Clone)]
+pub enum IpSource {
+    /// A "what is my IP" HTTP endpoint.
+    Http(HttpSource),
+    /// A cloud instance metadata service.
+    Metadata(MetadataSource),
+}
+
+impl IpSource {
+    /// Detects the public address of the requested family using the given client, which
+    /// is bound to that family. Metadata services are queried with their own client.
+    pub async fn fetch(&self, client: &Client, want_v6: bool) -> Result<IpAddr> {
+        match self {
+            IpSource::Http(source) => source.fetch(client).await,
+            IpSource::Metadata(source) => source.fetch(want_v6).await,
+        }
+    }
+}
+
+impl fmt::Display for IpSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpSource::Http(source) => write!(f, "{}", source.url()),
+            IpSource::Metadata(source) => write!(f, "{:?} metadata", source.provider()),
+        }
+    }
+}
@@ -52,3 +52,51 @@ impl<'de> Deserialize<'de> for Id {
         deserializer.deserialize_any(IdVisitor)
     }
 }
+
+/// Deserializes a number that DNSPod may send either as a JSON number or as a string,
+/// e.g. `"ttl": "600"`. An empty string yields the default value.
+pub fn lenient_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr + Default,
+{
+    struct NumberVisitor<T>(std::marker::PhantomData<T>);
+
+    impl<'de, T: std::str::FromStr + Default> Visitor<'de> for NumberVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a number or a numeric string")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            if value.trim().is_empty() {
+                return Ok(T::default());
+            }
+            value
+                .trim()
+                .parse()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            self.visit_str(&value.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(NumberVisitor(std::marker::PhantomData))
+}
+
+/// Deserializes an ID sent either as a string or as a number into a string.
+pub fn id_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Id::deserialize(deserializer).map(|id| id.to_string())
+}
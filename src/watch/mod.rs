@@ -0,0 +1,206 @@
+use crate::error::Result;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time;
+use tracing::trace;
+
+/// How long to wait for a burst of related events (e.g. address + route) to settle.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Watches the kernel for address and default-route changes.
+///
+/// On Linux this subscribes to rtnetlink `RTM_NEWADDR`/`RTM_DELADDR` and
+/// `RTM_NEWROUTE`/`RTM_DELROUTE` multicast groups. Other platforms are not supported.
+pub struct NetworkWatcher {
+    /// The number of changes reported by the background thread so far.
+    changes: watch::Receiver<u64>,
+    /// The number of changes already covered by a check.
+    seen: u64,
+}
+
+impl NetworkWatcher {
+    /// Opens the netlink socket and starts a background thread that listens for changes.
+    pub fn spawn() -> Result<Self> {
+        let (sender, changes) = watch::channel(0);
+        platform::spawn(sender)?;
+        Ok(Self { changes, seen: 0 })
+    }
+
+    /// Waits for a network change that no check has covered yet.
+    ///
+    /// Events arriving within a short window are coalesced into a single wake-up. If the
+    /// wait is cancelled, e.g. by the regular interval, pending changes are kept until
+    /// [`mark_seen`](Self::mark_seen) is called.
+    pub async fn changed(&mut self) {
+        while *self.changes.borrow_and_update() == self.seen {
+            if self.changes.changed().await.is_err() {
+                // The watcher thread stopped; no more changes will be reported.
+                std::future::pending::<()>().await;
+            }
+        }
+        time::sleep(DEBOUNCE).await;
+        trace!("Network change settled after {:?}.", DEBOUNCE);
+        self.mark_seen();
+    }
+
+    /// Records that a check is about to run, covering every change reported so far.
+    pub fn mark_seen(&mut self) {
+        self.seen = *self.changes.borrow_and_update();
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use crate::error::{DdnsError, Result};
+    use std::io;
+    use std::mem;
+    use tokio::sync::watch;
+    use tracing::{debug, trace, warn};
+
+    const NLMSG_HDRLEN: usize = mem::size_of::<libc::nlmsghdr>();
+
+    pub(super) fn spawn(changes: watch::Sender<u64>) -> Result<()> {
+        let fd = open_socket()
+            .map_err(|e| DdnsError::Config(format!("Failed to open rtnetlink socket: {}", e)))?;
+
+        std::thread::Builder::new()
+            .name("netlink-watch".to_string())
+            .spawn(move || listen(fd, changes))
+            .map_err(|e| {
+                DdnsError::Config(format!("Failed to start netlink watcher thread: {}", e))
+            })?;
+        Ok(())
+    }
+
+    /// (Private) Opens a NETLINK_ROUTE socket bound to the address and route groups.
+    fn open_socket() -> io::Result<libc::c_int> {
+        // SAFETY: plain socket syscalls; the fd is checked and closed on error.
+        unsafe {
+            let fd = libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            );
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut addr: libc::sockaddr_nl = mem::zeroed();
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_groups = (libc::RTMGRP_IPV4_IFADDR
+                | libc::RTMGRP_IPV6_IFADDR
+                | libc::RTMGRP_IPV4_ROUTE
+                | libc::RTMGRP_IPV6_ROUTE) as u32;
+
+            let rc = libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            );
+            if rc < 0 {
+                let err = io::Error::last_os_error();
+                libc::close(fd);
+                return Err(err);
+            }
+            Ok(fd)
+        }
+    }
+
+    /// (Private) Blocks on the socket and wakes the daemon on every relevant message.
+    fn listen(fd: libc::c_int, changes: watch::Sender<u64>) {
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            // SAFETY: `buf` is valid for writes of `buf.len()` bytes.
+            let n = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if n < 0 {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    // The kernel dropped messages; something changed, so check anyway.
+                    Some(libc::ENOBUFS) => {
+                        changes.send_modify(|count| *count = count.wrapping_add(1));
+                        continue;
+                    }
+                    _ => {
+                        warn!("🚨 Netlink watcher stopped: {}", err);
+                        // SAFETY: `fd` is owned by this thread and not used afterwards.
+                        unsafe { libc::close(fd) };
+                        return;
+                    }
+                }
+            }
+
+            if is_relevant(&buf[..n as usize]) {
+                debug!("🔔 Netlink reported an address or default route change.");
+                changes.send_modify(|count| *count = count.wrapping_add(1));
+            }
+        }
+    }
+
+    /// (Private) Returns true if the datagram contains a global address or default route change.
+    fn is_relevant(mut data: &[u8]) -> bool {
+        let mut relevant = false;
+        while data.len() >= NLMSG_HDRLEN {
+            let len = u32::from_ne_bytes([data[0], data[1], data[2], data[3]]) as usize;
+            let kind = u16::from_ne_bytes([data[4], data[5]]);
+            if len < NLMSG_HDRLEN || len > data.len() {
+                break;
+            }
+            let payload = &data[NLMSG_HDRLEN..len];
+
+            match kind {
+                // struct ifaddrmsg { family, prefixlen, flags, scope, index }
+                libc::RTM_NEWADDR | libc::RTM_DELADDR => {
+                    if payload.get(3) == Some(&libc::RT_SCOPE_UNIVERSE) {
+                        relevant = true;
+                    }
+                }
+                // struct rtmsg { family, dst_len, ... }; dst_len == 0 is a default route.
+                libc::RTM_NEWROUTE | libc::RTM_DELROUTE => {
+                    if payload.get(1) == Some(&0) {
+                        relevant = true;
+                    }
+                }
+                _ => trace!("Ignoring netlink message of type {}", kind),
+            }
+
+            // Messages are aligned to 4 bytes.
+            let aligned = (len + 3) & !3;
+            data = &data[aligned.min(data.len())..];
+        }
+        relevant
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use crate::error::{DdnsError, Result};
+    use tokio::sync::watch;
+
+    pub(super) fn spawn(_changes: watch::Sender<u64>) -> Result<()> {
+        Err(DdnsError::Config(
+            "Watching for network changes is only supported on Linux".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancelled_wait_keeps_the_change_and_bursts_coalesce() {
+        let (sender, changes) = watch::channel(0);
+        let mut watcher = NetworkWatcher { changes, seen: 0 };
+        sender.send_modify(|count| *count += 1);
+        sender.send_modify(|count| *count += 1);
+
+        // The interval fires during the debounce.
+        let interrupted = time::timeout(Duration::from_millis(100), watcher.changed()).await;
+        assert!(interrupted.is_err());
+
+        let settled = time::timeout(DEBOUNCE * 2, watcher.changed()).await;
+        assert!(settled.is_ok(), "the pending change was lost");
+        assert_eq!(watcher.seen, 2);
+    }
+}